@@ -5,8 +5,10 @@
 /// This function analyzes the case pattern of a word and returns
 /// an appropriate suffix to indicate its case style:
 /// - "_A" for ALL UPPERCASE words (length > 1)
+/// - "_T" for Title Case phrases of more than one word
 /// - "_F" for First letter uppercase words
-/// - "" (empty string) for lowercase words
+/// - "" (empty string) for lowercase words and for words in scripts
+///   without case, such as Chinese or Thai
 ///
 /// # Parameters
 ///
@@ -20,14 +22,21 @@ pub fn determine_case_suffix(word: &str) -> &'static str {
         return "";
     }
 
+    // Letters without case have nothing to record.
+    if word.chars().any(char::is_alphabetic) && !word.chars().any(has_case) {
+        return "";
+    }
+
     let first_char = word.chars().next().unwrap();
     let is_first_upper = first_char.is_uppercase();
 
     // Check if all characters are uppercase
-    let is_all_upper = word.chars().all(|c| !c.is_alphabetic() || c.is_uppercase());
+    let is_all_upper = word.chars().all(|c| !has_case(c) || c.is_uppercase());
 
     if is_all_upper && word.len() > 1 {
         "_A" // All uppercase
+    } else if is_title_case_phrase(word) {
+        "_T" // Every word capitalized
     } else if is_first_upper {
         "_F" // First letter uppercase
     } else {
@@ -35,6 +44,58 @@ pub fn determine_case_suffix(word: &str) -> &'static str {
     }
 }
 
+/// Checks whether a phrase of two or more words has every word capitalized
+/// and all other letters in lowercase.
+fn is_title_case_phrase(phrase: &str) -> bool {
+    let words: Vec<&str> = phrase
+        .split_whitespace()
+        .filter(|word| word.chars().any(has_case))
+        .collect();
+
+    words.len() > 1
+        && words.iter().all(|word| {
+            let mut letters = word.chars().filter(|c| has_case(*c));
+            letters.next().is_some_and(char::is_uppercase) && letters.all(char::is_lowercase)
+        })
+}
+
+/// Whether a character is a letter with distinct upper and lowercase forms.
+///
+/// Scripts such as Han, Kana and Thai are alphabetic but have no case, so
+/// they never carry case information.
+fn has_case(c: char) -> bool {
+    c.is_uppercase() || c.is_lowercase()
+}
+
+/// Capitalizes the first cased letter of every whitespace-separated word.
+///
+/// # Parameters
+///
+/// * `s` - The phrase to capitalize, normally in lowercase
+/// * `locale` - The locale whose case mappings are used
+///
+/// # Returns
+///
+/// A new string in title case.
+pub fn to_title_case(s: &str, locale: Locale) -> String {
+    let mut titled = String::with_capacity(s.len());
+    let mut at_word_start = true;
+
+    for character in s.chars() {
+        if at_word_start && has_case(character) {
+            titled.push_str(&locale.upper_char(character));
+            at_word_start = false;
+        } else {
+            titled.push(character);
+            if character.is_whitespace() {
+                at_word_start = true;
+            }
+        }
+    }
+
+    titled
+}
+
 /// Capitalizes the first letter of a string.
 ///
 /// # Parameters
@@ -45,9 +106,294 @@ pub fn determine_case_suffix(word: &str) -> &'static str {
 ///
 /// A new string with the first letter capitalized.
 pub fn capitalize_first(s: &str) -> String {
+    capitalize_first_in(s, Locale::Neutral)
+}
+
+/// Capitalizes the first letter of a string using a locale's case mappings,
+/// so that "istanbul" becomes "İstanbul" in Turkish.
+///
+/// # Parameters
+///
+/// * `s` - The string to capitalize
+/// * `locale` - The locale whose case mappings are used
+///
+/// # Returns
+///
+/// A new string with the first letter capitalized.
+pub fn capitalize_first_in(s: &str, locale: Locale) -> String {
     let mut chars = s.chars();
     match chars.next() {
         None => String::new(),
-        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+        Some(first) => locale.upper_char(first) + chars.as_str(),
+    }
+}
+
+/// Returns the number of hex digits a `_M` case mask needs for `word`.
+///
+/// Each cased letter takes one bit, so the width only depends on the word
+/// and the decoder can read the mask without a terminator.
+pub fn case_mask_width(word: &str) -> usize {
+    word.chars().filter(|c| has_case(*c)).count().div_ceil(4)
+}
+
+/// Encodes exactly which letters of a word are uppercase.
+///
+/// The suffix is "_M" followed by a fixed-width uppercase hex bitmask in
+/// which bit `i` is set when the `i`-th cased letter is uppercase, e.g.
+/// "_M05" for "McDonald" and "_M2" for "eBay".
+///
+/// # Parameters
+///
+/// * `word` - The word to analyze
+///
+/// # Returns
+///
+/// The "_M" suffix describing the word's casing.
+pub fn mixed_case_suffix(word: &str) -> String {
+    let uppercase_bits: Vec<bool> = word
+        .chars()
+        .filter(|c| has_case(*c))
+        .map(char::is_uppercase)
+        .collect();
+    let width = uppercase_bits.len().div_ceil(4);
+
+    let digits: String = (0..width)
+        .rev()
+        .map(|digit| {
+            let nibble = (0..4)
+                .filter(|bit| uppercase_bits.get(digit * 4 + bit) == Some(&true))
+                .fold(0u32, |value, bit| value | (1 << bit));
+            char::from_digit(nibble, 16)
+                .unwrap_or('0')
+                .to_ascii_uppercase()
+        })
+        .collect();
+
+    format!("_M{}", digits)
+}
+
+/// Applies a hex case mask produced by `mixed_case_suffix` to a word.
+///
+/// # Parameters
+///
+/// * `word` - The word to recase, normally in lowercase
+/// * `mask` - The hex digits following "_M"
+/// * `locale` - The locale whose case mappings are used
+///
+/// # Returns
+///
+/// The recased word, or `None` if the mask is not valid for the word.
+pub fn apply_case_mask(word: &str, mask: &str, locale: Locale) -> Option<String> {
+    if mask.len() != case_mask_width(word)
+        || !mask
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('A'..='F').contains(&c))
+    {
+        return None;
+    }
+
+    let nibbles: Vec<u32> = mask.chars().rev().filter_map(|c| c.to_digit(16)).collect();
+    let chars: Vec<char> = word.chars().collect();
+    let mut letter_index = 0;
+    let mut recased = String::with_capacity(word.len());
+
+    for (index, &character) in chars.iter().enumerate() {
+        if !has_case(character) {
+            recased.push(character);
+            continue;
+        }
+
+        let is_upper = nibbles[letter_index / 4] & (1 << (letter_index % 4)) != 0;
+        if is_upper {
+            recased.push_str(&locale.upper_char(character));
+        } else {
+            recased.push_str(&locale.lower_char(character, ends_word(&chars, index)));
+        }
+        letter_index += 1;
+    }
+
+    Some(recased)
+}
+
+/// Whether the letter at `index` ends a word of two or more letters, where a
+/// Greek sigma is written "ς".
+fn ends_word(chars: &[char], index: usize) -> bool {
+    index > 0
+        && chars[index - 1].is_alphabetic()
+        && !chars.get(index + 1).is_some_and(|c| c.is_alphabetic())
+}
+
+/// A locale whose case mappings differ from the Unicode defaults.
+///
+/// The locale decides how mask words are case-folded when matching and how
+/// their case variants are rebuilt when decoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    /// The locale-independent Unicode case mappings.
+    #[default]
+    Neutral,
+    /// Turkish and Azerbaijani: "i" pairs with "İ" and "ı" with "I".
+    Turkish,
+    /// Greek: accents are dropped in uppercase and match unaccented letters.
+    Greek,
+    /// German: "ß" matches "SS" and "ss".
+    German,
+}
+
+// Greek letters that are interchangeable once accents are ignored.
+const GREEK_ACCENT_CLASSES: &[&str] = &[
+    "ΑΆαά",
+    "ΕΈεέ",
+    "ΗΉηή",
+    "ΙΊΪιίϊΐ",
+    "ΟΌοό",
+    "ΥΎΫυύϋΰ",
+    "ΩΏωώ",
+    "Σσς",
+];
+
+impl Locale {
+    /// Parses a BCP 47 language tag such as `"tr"` or `"de-CH"`.
+    ///
+    /// Languages without special case mappings yield `Locale::Neutral`.
+    pub fn from_code(code: &str) -> Self {
+        let language = code
+            .trim()
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        match language.as_str() {
+            "tr" | "az" => Locale::Turkish,
+            "el" => Locale::Greek,
+            "de" => Locale::German,
+            _ => Locale::Neutral,
+        }
+    }
+
+    /// Returns the language code for the locale, or `None` for the neutral
+    /// locale.
+    pub fn code(self) -> Option<&'static str> {
+        match self {
+            Locale::Neutral => None,
+            Locale::Turkish => Some("tr"),
+            Locale::Greek => Some("el"),
+            Locale::German => Some("de"),
+        }
+    }
+
+    /// Lowercases a string. In Greek, every sigma is also written by its
+    /// position, so "Οδυσσέασ" becomes "οδυσσέας".
+    pub fn to_lowercase(self, s: &str) -> String {
+        match self {
+            Locale::Turkish => s.replace('I', "ı").replace('İ', "i").to_lowercase(),
+            Locale::Greek => {
+                let chars: Vec<char> = s.chars().collect();
+                chars
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &c)| self.lower_char(c, ends_word(&chars, index)))
+                    .collect()
+            }
+            _ => s.to_lowercase(),
+        }
+    }
+
+    /// Uppercases a string.
+    pub fn to_uppercase(self, s: &str) -> String {
+        match self {
+            Locale::Turkish => s.replace('i', "İ").to_uppercase(),
+            Locale::Greek => s
+                .chars()
+                .map(strip_greek_accent)
+                .collect::<String>()
+                .to_uppercase(),
+            _ => s.to_uppercase(),
+        }
+    }
+
+    /// Uppercases a single letter, keeping any Greek accent.
+    pub fn upper_char(self, c: char) -> String {
+        match (self, c) {
+            (Locale::Turkish, 'i') => "İ".to_string(),
+            _ => c.to_uppercase().collect(),
+        }
+    }
+
+    /// Lowercases a single letter, writing a Greek sigma as "ς" when it
+    /// ends a word and as "σ" elsewhere.
+    pub fn lower_char(self, c: char, ends_word: bool) -> String {
+        match (self, c) {
+            (Locale::Turkish, 'I') => "ı".to_string(),
+            (Locale::Turkish, 'İ') => "i".to_string(),
+            (_, 'Σ' | 'σ' | 'ς') if ends_word => "ς".to_string(),
+            (_, 'Σ' | 'σ' | 'ς') => "σ".to_string(),
+            _ => c.to_lowercase().collect(),
+        }
+    }
+
+    /// Escapes text for a case-insensitive regex, widening the letters whose
+    /// case folding depends on the locale.
+    pub fn escape_pattern(self, text: &str) -> String {
+        if self == Locale::Neutral {
+            return regex::escape(text);
+        }
+
+        let mut pattern = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+
+        while let Some(character) = chars.next() {
+            match (self, character) {
+                // Unicode folds "i" with "I", so the Turkish pairs opt out of
+                // case-insensitive matching.
+                (Locale::Turkish, 'i' | 'İ') => pattern.push_str("(?-i:[iİ])"),
+                (Locale::Turkish, 'ı' | 'I') => pattern.push_str("(?-i:[ıI])"),
+                (Locale::German, 'ß' | 'ẞ') => pattern.push_str("(?:ß|ss)"),
+                (Locale::German, 's' | 'S')
+                    if chars.peek().is_some_and(|next| matches!(next, 's' | 'S')) =>
+                {
+                    chars.next();
+                    pattern.push_str("(?:ss|ß)");
+                }
+                (Locale::Greek, _) => match GREEK_ACCENT_CLASSES
+                    .iter()
+                    .find(|class| class.contains(character))
+                {
+                    Some(class) => {
+                        pattern.push('[');
+                        pattern.push_str(class);
+                        pattern.push(']');
+                    }
+                    None => pattern.push_str(&regex::escape(character.encode_utf8(&mut [0; 4]))),
+                },
+                _ => pattern.push_str(&regex::escape(character.encode_utf8(&mut [0; 4]))),
+            }
+        }
+
+        pattern
+    }
+}
+
+/// Replaces an accented Greek vowel with its unaccented form.
+fn strip_greek_accent(c: char) -> char {
+    match c {
+        'Ά' => 'Α',
+        'Έ' => 'Ε',
+        'Ή' => 'Η',
+        'Ί' => 'Ι',
+        'Ό' => 'Ο',
+        'Ύ' => 'Υ',
+        'Ώ' => 'Ω',
+        'ά' => 'α',
+        'έ' => 'ε',
+        'ή' => 'η',
+        'ί' => 'ι',
+        'ό' => 'ο',
+        'ύ' => 'υ',
+        'ώ' => 'ω',
+        'ΐ' => 'ϊ',
+        'ΰ' => 'ϋ',
+        _ => c,
     }
 }
@@ -0,0 +1,121 @@
+//! Masking of values that are only sensitive because of what precedes them.
+//!
+//! A rule names a keyword such as `password:` or `Authorization: Bearer` and
+//! describes the value that follows it: either a number of
+//! whitespace-separated tokens, or everything up to a delimiter.
+
+use regex::RegexBuilder;
+
+/// How far the value after a rule's keyword extends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ContextValue {
+    /// The next N whitespace-separated tokens.
+    Tokens(usize),
+    /// Everything up to, but not including, the delimiter or the end of text.
+    UpTo(String),
+}
+
+/// A keyword and the value after it that should be masked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ContextRule {
+    pub keyword: String,
+    pub value: ContextValue,
+    /// Ranks the rule's values against overlapping matches under the priority
+    /// policy.
+    pub category: Option<String>,
+}
+
+/// Finds the spans of all values selected by the given rules.
+///
+/// Keywords are matched case-insensitively, and those ending in a letter,
+/// digit or underscore only where a word ends, so `SSN` does not match in
+/// `SSNs`. Whitespace, `:` and `=` between the keyword and its value are
+/// skipped. Overlapping spans are resolved in
+/// favor of the one that starts first.
+///
+/// # Parameters
+///
+/// * `text` - The text to search
+/// * `rules` - The rules to apply
+///
+/// # Returns
+///
+/// Non-overlapping `(start, end, rule_index)` byte spans, sorted by start.
+pub(crate) fn find_context_spans(text: &str, rules: &[ContextRule]) -> Vec<(usize, usize, usize)> {
+    let mut spans: Vec<(usize, usize, usize)> = Vec::new();
+
+    for (rule_index, rule) in rules.iter().enumerate() {
+        if rule.keyword.is_empty() {
+            continue;
+        }
+
+        let boundary = if rule
+            .keyword
+            .ends_with(|c: char| c.is_alphanumeric() || c == '_')
+        {
+            r"\b"
+        } else {
+            ""
+        };
+        let pattern = format!(r"{}{}[\s:=]*", regex::escape(&rule.keyword), boundary);
+        let Ok(keyword) = RegexBuilder::new(&pattern).case_insensitive(true).build() else {
+            continue;
+        };
+
+        for found in keyword.find_iter(text) {
+            let start = found.end();
+            let end = start + value_len(&text[start..], &rule.value);
+            if end > start {
+                spans.push((start, end, rule_index));
+            }
+        }
+    }
+
+    spans.sort_unstable();
+    let mut resolved: Vec<(usize, usize, usize)> = Vec::with_capacity(spans.len());
+    for span in spans {
+        if resolved.last().is_none_or(|last| last.1 <= span.0) {
+            resolved.push(span);
+        }
+    }
+    resolved
+}
+
+/// Returns the byte length of the value at the start of `rest`. Sentence
+/// punctuation after the last token is not part of the value.
+fn value_len(rest: &str, value: &ContextValue) -> usize {
+    match value {
+        ContextValue::Tokens(0) => 0,
+        ContextValue::Tokens(count) => {
+            let mut end = 0;
+            let mut tokens = 0;
+            let mut in_token = false;
+
+            for (index, character) in rest.char_indices() {
+                if character.is_whitespace() {
+                    if in_token {
+                        tokens += 1;
+                        in_token = false;
+                        if tokens == *count {
+                            break;
+                        }
+                    }
+                } else {
+                    in_token = true;
+                    end = index + character.len_utf8();
+                }
+            }
+
+            rest[..end]
+                .trim_end_matches(['.', ',', ';', ':', '!', '?'])
+                .len()
+        }
+        ContextValue::UpTo(delimiter) => {
+            let value = match rest.find(delimiter.as_str()) {
+                Some(index) if !delimiter.is_empty() => &rest[..index],
+                _ => rest,
+            };
+            value.trim_end().len()
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Matching of inflected forms of mask words.
+//!
+//! English uses explicit plural and possessive rules. German, French and
+//! Spanish use a light Snowball-style stemmer: an inflectional ending is only
+//! removed when it lies inside the word's R1 region, and the resulting stem is
+//! matched together with one of the endings of the same inflection, such as
+//! the singular and plural of the same gender. A stem is never matched bare,
+//! so Spanish `Carlos` matches `Carlo` but not `Carla` or `carl`, and French
+//! `Paris` does not match `pari`.
+//!
+//! Patterns are written in lowercase and rely on the caller compiling them
+//! case-insensitively.
+
+use crate::matcher::TermSyntax;
+
+/// A language whose inflection rules can be applied to mask words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    German,
+    French,
+    Spanish,
+}
+
+const GERMAN_VOWELS: &str = "aeiouyäöü";
+const FRENCH_VOWELS: &str = "aeiouyâàëéêèïîôûù";
+const SPANISH_VOWELS: &str = "aeiouáéíóúü";
+
+// Every ending the stemmer removes, longest first, with the endings of the
+// same inflection that are matched in its place. The empty ending lists what
+// may follow a word from which nothing is removed.
+const GERMAN_ENDINGS: &[(&str, &[&str])] = &[
+    ("ern", &["ern", "er"]),
+    ("em", &["em", "e", "en", "es"]),
+    ("en", &["en", "e", "es"]),
+    ("er", &["er", "ern", "ers"]),
+    ("es", &["es", "e", "en"]),
+    ("e", &["e", "en", "es"]),
+    ("s", &["s"]),
+    ("n", &["n"]),
+    ("", &["", "s", "es", "n", "en"]),
+];
+const FRENCH_ENDINGS: &[(&str, &[&str])] = &[
+    ("ales", &["ales", "ale"]),
+    ("aux", &["aux", "al", "als"]),
+    ("ale", &["ale", "ales"]),
+    ("als", &["als", "al", "aux"]),
+    ("al", &["al", "aux", "als"]),
+    ("es", &["es", "e"]),
+    ("e", &["e", "es"]),
+    ("s", &["s"]),
+    ("x", &["x"]),
+    ("", &["", "s", "x"]),
+];
+const SPANISH_ENDINGS: &[(&str, &[&str])] = &[
+    ("ces", &["ces", "z"]),
+    ("es", &["es", "e"]),
+    ("as", &["as", "a"]),
+    ("os", &["os", "o"]),
+    ("a", &["a", "as"]),
+    ("o", &["o", "os"]),
+    ("e", &["e", "es"]),
+    ("s", &["s"]),
+    ("z", &["z", "ces"]),
+    ("", &["", "es"]),
+];
+
+impl Language {
+    /// Parses an ISO 639-1 code or English language name.
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim().to_lowercase().as_str() {
+            "en" | "english" => Some(Language::English),
+            "de" | "german" => Some(Language::German),
+            "fr" | "french" => Some(Language::French),
+            "es" | "spanish" => Some(Language::Spanish),
+            _ => None,
+        }
+    }
+
+    /// Returns the ISO 639-1 code for the language.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+            Language::French => "fr",
+            Language::Spanish => "es",
+        }
+    }
+}
+
+/// Builds a regex fragment matching a mask word and its inflected forms.
+///
+/// Only the last word of a multi-word phrase is inflected.
+///
+/// # Parameters
+///
+/// * `word` - The mask word as listed by the user
+/// * `language` - The language whose rules are applied
+/// * `syntax` - How the literal parts of the word are escaped
+///
+/// # Returns
+///
+/// An unanchored regex fragment without capture groups.
+pub fn inflected_pattern(word: &str, language: Language, syntax: TermSyntax) -> String {
+    let split = word
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map(|(index, c)| index + c.len_utf8())
+        .unwrap_or(0);
+    let (head, last) = word.split_at(split);
+
+    if last.is_empty() || !last.chars().last().is_some_and(char::is_alphabetic) {
+        return syntax.escape(word);
+    }
+
+    let inflected = match language {
+        Language::English => english_pattern(last, syntax),
+        Language::German => stemmed_pattern(last, GERMAN_VOWELS, GERMAN_ENDINGS, 3, syntax),
+        Language::French => stemmed_pattern(last, FRENCH_VOWELS, FRENCH_ENDINGS, 0, syntax),
+        Language::Spanish => stemmed_pattern(last, SPANISH_VOWELS, SPANISH_ENDINGS, 0, syntax),
+    };
+
+    format!("{}{}", syntax.escape(head), inflected)
+}
+
+fn english_pattern(word: &str, syntax: TermSyntax) -> String {
+    const APOSTROPHE: &str = "['’]";
+
+    let lower = word.to_lowercase();
+    let mut reversed = lower.chars().rev();
+    let last = reversed.next().unwrap_or_default();
+    let before_last = reversed.next().unwrap_or_default();
+    let is_vowel = |c: char| "aeiou".contains(c);
+
+    if last == 'y' && before_last.is_ascii_alphabetic() && !is_vowel(before_last) {
+        let stem = syntax.escape(&word[..word.len() - 1]);
+        return format!("{}(?:y(?:{a}s)?|ies{a}?)", stem, a = APOSTROPHE);
+    }
+
+    if lower.ends_with("fe") {
+        let stem = syntax.escape(&word[..word.len() - 2]);
+        return format!("{}(?:fe(?:s|{a}s)?|ves{a}?)", stem, a = APOSTROPHE);
+    }
+
+    if last == 'f' && before_last != 'f' {
+        let stem = syntax.escape(&word[..word.len() - 1]);
+        return format!("{}(?:f(?:s|{a}s)?|ves{a}?)", stem, a = APOSTROPHE);
+    }
+
+    let escaped = syntax.escape(word);
+    if ["s", "x", "z", "ch", "sh"]
+        .iter()
+        .any(|ending| lower.ends_with(ending))
+    {
+        format!("{}(?:es{a}?|{a}s?)?", escaped, a = APOSTROPHE)
+    } else {
+        format!("{}(?:s{a}?|{a}s)?", escaped, a = APOSTROPHE)
+    }
+}
+
+fn stemmed_pattern(
+    word: &str,
+    vowels: &str,
+    endings: &[(&str, &[&str])],
+    min_r1: usize,
+    syntax: TermSyntax,
+) -> String {
+    let chars: Vec<char> = word.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let r1 = r1_start(&lower, vowels).max(min_r1);
+
+    let Some((removed, inflection)) = endings.iter().find(|(ending, _)| {
+        let ending_len = ending.chars().count();
+        lower.len() >= r1 + ending_len
+            && lower[lower.len() - ending_len..]
+                .iter()
+                .copied()
+                .eq(ending.chars())
+    }) else {
+        return syntax.escape(word);
+    };
+
+    let stem: String = chars[..chars.len() - removed.chars().count()]
+        .iter()
+        .collect();
+    let mut alternatives: Vec<&str> = inflection
+        .iter()
+        .copied()
+        .filter(|ending| !ending.is_empty())
+        .collect();
+    // Longest first, so the regex alternation prefers the longest ending.
+    alternatives.sort_by_key(|ending| std::cmp::Reverse(ending.len()));
+    let optional = if inflection.contains(&"") { "?" } else { "" };
+
+    format!(
+        "{}(?:{}){}",
+        syntax.escape(&stem),
+        alternatives
+            .iter()
+            .map(|ending| regex::escape(ending))
+            .collect::<Vec<_>>()
+            .join("|"),
+        optional
+    )
+}
+
+/// Returns the char index where the Snowball R1 region begins: just after the
+/// first non-vowel that follows a vowel.
+fn r1_start(word: &[char], vowels: &str) -> usize {
+    word.windows(2)
+        .position(|pair| vowels.contains(pair[0]) && !vowels.contains(pair[1]))
+        .map(|index| index + 2)
+        .unwrap_or(word.len())
+}
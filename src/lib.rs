@@ -1,11 +1,28 @@
 mod case_utils;
+mod context_rules;
+mod inflection;
+mod mapping;
+mod mask_style;
+mod matcher;
+mod options;
+mod overlap;
+mod placeholder;
+mod pseudonym;
+mod reveal;
+mod segmentation;
 mod text_processor;
+mod token;
 mod utils;
+mod word_list;
 
 use js_sys::Set;
 use wasm_bindgen::prelude::*;
 
-pub use case_utils::{capitalize_first, determine_case_suffix};
+pub use case_utils::{capitalize_first, capitalize_first_in, determine_case_suffix, Locale};
+pub use mapping::{DecodeResult, FieldMapping, LenientMatch, MaskResult, PseudonymMapping};
+pub use options::MaskOptions;
+pub use reveal::{AsteriskResult, RevealedOccurrence};
+pub use token::{TokenKey, TokenVault};
 
 /// Masks specified words in text with asterisks.
 ///
@@ -25,10 +42,205 @@ pub fn mask_text(text: String, mask_words: &Set) -> String {
     text_processor::mask_text(text, mask_words)
 }
 
+/// Masks specified words in text with asterisks using the given options.
+///
+/// The options can replace the asterisk with another glyph and choose
+/// whether masks count bytes, count characters or have a fixed length.
+///
+/// # Parameters
+///
+/// * `text` - The original text to mask
+/// * `mask_words` - A JavaScript Set containing the words to mask
+/// * `options` - Options controlling how words are matched
+///
+/// # Returns
+///
+/// A String with the specified words masked with asterisks.
+#[wasm_bindgen]
+pub fn mask_text_with_options(text: String, mask_words: &Set, options: &MaskOptions) -> String {
+    text_processor::mask_text_with_options(text, mask_words, options)
+}
+
+/// Masks specified words in text with asterisks and reports what partial
+/// masking left visible.
+///
+/// Entries with `keepFirst`, `keepLast`, `keepDomain` or `keepCountryCode`
+/// properties, and entries and context rules of categories given a reveal
+/// with `MaskOptions.set_category_reveal`, keep those characters visible.
+///
+/// # Parameters
+///
+/// * `text` - The original text to mask
+/// * `mask_words` - A JavaScript Set containing the words to mask
+/// * `options` - Options controlling how words are matched
+///
+/// # Returns
+///
+/// An AsteriskResult holding the masked text and the partially masked
+/// occurrences.
+#[wasm_bindgen]
+pub fn mask_text_with_details(
+    text: String,
+    mask_words: &Set,
+    options: &MaskOptions,
+) -> AsteriskResult {
+    text_processor::mask_text_with_details(text, mask_words, options)
+}
+
+/// Masks specified words in text with realistic pseudonyms.
+///
+/// Names, companies, emails and cities are replaced with fake values of the
+/// same kind, chosen by each entry's or context rule's category or else by
+/// the value's shape. Other values keep their shape with different letters
+/// and digits. Each value keeps its pseudonym for as long as the same
+/// mapping is passed in.
+///
+/// # Parameters
+///
+/// * `text` - The original text to mask
+/// * `mask_words` - A JavaScript Set containing the words to mask
+/// * `options` - Options controlling how words are matched
+/// * `mapping` - The session's PseudonymMapping, which records every pseudonym
+///
+/// # Returns
+///
+/// A String with the specified words replaced by pseudonyms.
+#[wasm_bindgen]
+pub fn mask_text_with_pseudonyms(
+    text: String,
+    mask_words: &Set,
+    options: &MaskOptions,
+    mapping: &mut PseudonymMapping,
+) -> String {
+    text_processor::mask_text_with_pseudonyms(text, mask_words, options, mapping)
+}
+
+/// Masks specified words in text with values of the same shape.
+///
+/// Every digit is replaced with a digit and every letter with a letter of
+/// the same case, while separators are kept, so `4111-1111-1111-1111` becomes
+/// another grouped 16-digit number that still passes the Luhn check. The
+/// replacements are chosen from the mapping's key and seed, stay the same for
+/// as long as the same mapping is passed in, and can be reversed with
+/// `restore_pseudonyms`.
+///
+/// # Parameters
+///
+/// * `text` - The original text to mask
+/// * `mask_words` - A JavaScript Set containing the words to mask
+/// * `options` - Options controlling how words are matched
+/// * `mapping` - The session's PseudonymMapping, which records every replacement
+///
+/// # Returns
+///
+/// A String with the specified words replaced by values of the same shape.
+#[wasm_bindgen]
+pub fn mask_text_preserving_format(
+    text: String,
+    mask_words: &Set,
+    options: &MaskOptions,
+    mapping: &mut PseudonymMapping,
+) -> String {
+    text_processor::mask_text_preserving_format(text, mask_words, options, mapping)
+}
+
+/// Masks specified words in text with keyed tokens such as `TOK_3f9a2c`.
+///
+/// A token is derived from the word, the key and its number of digits
+/// alone, so the same word gets the same token in every document masked with
+/// the same key. Aliases and name parts get their word's token followed by a
+/// modifier such as `_V1` or `_LAST`, and the vault records their exact
+/// text. Case suffixes such as `_F` and `_A` follow as in field placeholders.
+/// Every token is recorded in the vault, which reports tokens shared by two
+/// words in `collisions`.
+///
+/// # Parameters
+///
+/// * `text` - The original text to mask
+/// * `mask_words` - A JavaScript Set containing the words to mask
+/// * `options` - Options controlling how words are matched
+/// * `key` - The TokenKey holding the shared secret
+/// * `vault` - The TokenVault that receives every token and its word
+///
+/// # Returns
+///
+/// A String with the specified words replaced by tokens.
+#[wasm_bindgen]
+pub fn mask_text_with_tokens(
+    text: String,
+    mask_words: &Set,
+    options: &MaskOptions,
+    key: &TokenKey,
+    vault: &mut TokenVault,
+) -> String {
+    text_processor::mask_text_with_tokens(text, mask_words, options, key, vault)
+}
+
+/// Decodes keyed tokens using the words recorded in a vault.
+///
+/// Tokens missing from the vault are left unchanged.
+///
+/// # Parameters
+///
+/// * `text` - The text containing tokens
+/// * `vault` - The TokenVault holding the word behind every token
+/// * `options` - The options the text was masked with
+///
+/// # Returns
+///
+/// A String with every known token replaced by its word.
+#[wasm_bindgen]
+pub fn decode_tokens(text: String, vault: &TokenVault, options: &MaskOptions) -> String {
+    text_processor::decode_tokens(text, vault, options)
+}
+
+/// Replaces every known token with the token of the same word under a new
+/// key, keeping case suffixes, and records the new tokens in the vault.
+///
+/// # Parameters
+///
+/// * `text` - The text containing tokens
+/// * `vault` - The TokenVault holding the word behind every token
+/// * `new_key` - The TokenKey to rotate to
+///
+/// # Returns
+///
+/// A String with tokens rewritten under the new key.
+#[wasm_bindgen]
+pub fn rotate_token_key(text: String, vault: &mut TokenVault, new_key: &TokenKey) -> String {
+    text_processor::rotate_token_key(text, vault, new_key)
+}
+
+/// Replaces the pseudonyms recorded in a mapping with the text they replaced.
+///
+/// # Parameters
+///
+/// * `text` - The text containing pseudonyms
+/// * `mapping` - The PseudonymMapping used to mask the text
+///
+/// # Returns
+///
+/// A String with every recorded pseudonym replaced by its original text.
+#[wasm_bindgen]
+pub fn restore_pseudonyms(text: String, mapping: &PseudonymMapping) -> String {
+    text_processor::restore_pseudonyms(text, mapping)
+}
+
 /// Masks specified words in text with numbered field placeholders.
 ///
 /// Replaces each occurrence of words from the provided set with field placeholders
-/// in the format "FIELD_N". Preserves case sensitivity with appropriate suffixes.
+/// in the format "FIELD_N". Preserves case sensitivity with appropriate suffixes:
+/// `_F` and `_A` for capitalized and uppercase words, `_T` for title-cased
+/// phrases, and a `_M` hex mask of the uppercase letters for mixed-case words
+/// such as "McDonald".
+///
+/// Items of the set may also be `{ word, aliases }` objects. Every alias masks
+/// to the word's field number with a `_Vk` suffix naming the alias, so that it
+/// decodes back to the alias text.
+///
+/// Text that already looks like a placeholder, such as a literal `FIELD_3`,
+/// is escaped as `FIELD_\3` so that decoding restores it untouched instead
+/// of replacing it with a mask word.
 ///
 /// # Parameters
 ///
@@ -43,10 +255,35 @@ pub fn mask_text_with_fields(text: String, mask_words: &Set) -> String {
     text_processor::mask_text_with_fields(text, mask_words)
 }
 
+/// Masks specified words with field placeholders and returns the mapping.
+///
+/// Behaves like `mask_text_with_fields`, but honors the given options and
+/// records the exact text behind placeholders that the case suffixes alone
+/// cannot restore, such as inflected forms.
+///
+/// # Parameters
+///
+/// * `text` - The original text to mask
+/// * `mask_words` - A JavaScript Set containing the words to mask
+/// * `options` - Options controlling how words are matched
+///
+/// # Returns
+///
+/// A MaskResult holding the masked text and its FieldMapping.
+#[wasm_bindgen]
+pub fn mask_text_with_fields_and_mapping(
+    text: String,
+    mask_words: &Set,
+    options: &MaskOptions,
+) -> MaskResult {
+    text_processor::mask_text_with_fields_and_mapping(text, mask_words, options)
+}
+
 /// Decodes text that was previously masked with field placeholders.
 ///
 /// Replaces each field placeholder (FIELD_N) with its corresponding original word.
-/// Handles different case variants using the appropriate suffixes.
+/// Handles different case variants using the appropriate suffixes, and
+/// restores escaped placeholder-like text from the original.
 ///
 /// # Parameters
 ///
@@ -60,3 +297,108 @@ pub fn mask_text_with_fields(text: String, mask_words: &Set) -> String {
 pub fn decode_obfuscated_text(text: String, mask_words: &Set) -> String {
     text_processor::decode_obfuscated_text(text, mask_words)
 }
+
+/// Decodes masked text using the given options.
+///
+/// Behaves like `decode_obfuscated_text`, but rebuilds case variants with the
+/// case mappings of the options' locale and recognizes placeholders written
+/// with the options' placeholder template and suffix separator.
+///
+/// # Parameters
+///
+/// * `text` - The obfuscated text to decode
+/// * `mask_words` - A JavaScript Set containing the original words
+/// * `options` - The options the text was masked with
+///
+/// # Returns
+///
+/// A String with field placeholders replaced with their original words.
+#[wasm_bindgen]
+pub fn decode_obfuscated_text_with_options(
+    text: String,
+    mask_words: &Set,
+    options: &MaskOptions,
+) -> String {
+    text_processor::decode_obfuscated_text_with_options(text, mask_words, options)
+}
+
+/// Decodes text that mixes documents masked in separate sessions.
+///
+/// Each document must have been masked with its own session namespace, so
+/// that `FIELD_a7k2_1` and `FIELD_q03x_1` decode with their own mappings.
+///
+/// # Parameters
+///
+/// * `text` - The obfuscated text to decode
+/// * `mappings` - The FieldMapping of every session in the text
+///
+/// # Returns
+///
+/// A String with the placeholders of every session replaced with their
+/// original text.
+#[wasm_bindgen]
+pub fn decode_with_mappings(text: String, mappings: Vec<FieldMapping>) -> String {
+    text_processor::decode_with_mappings(text, &mappings)
+}
+
+/// Decodes text using the mapping returned by a masking pass and reports the
+/// placeholders it could not decode.
+///
+/// Behaves like `decode_with_mapping`. When the placeholders were written
+/// with check characters, those that fail validation, such as a `FIELD_7`
+/// the masking pass never emitted, are left in the text and listed.
+///
+/// # Parameters
+///
+/// * `text` - The obfuscated text to decode
+/// * `mapping` - The FieldMapping returned alongside the masked text
+///
+/// # Returns
+///
+/// A DecodeResult holding the decoded text and the rejected placeholders.
+#[wasm_bindgen]
+pub fn decode_with_details(text: String, mapping: &FieldMapping) -> DecodeResult {
+    text_processor::decode_with_details(text, mapping)
+}
+
+/// Decodes text using the mapping returned by a masking pass, also accepting
+/// placeholders that were mangled in common ways.
+///
+/// Besides the canonical forms, this reads placeholders whose text differs in
+/// case or writes underscores as spaces or hyphens, such as `Field_3`,
+/// `FIELD 3` or `FIELD-3`, and lowercase modifiers such as `field_3_a`,
+/// whose case modifier still applies. Surrounding text such as the `**` of
+/// `**FIELD_3**` or the `'s` of `FIELD_3's` is kept around the decoded word.
+/// Every non-canonical placeholder it accepted is reported.
+///
+/// # Parameters
+///
+/// * `text` - The obfuscated text to decode
+/// * `mapping` - The FieldMapping returned alongside the masked text
+///
+/// # Returns
+///
+/// A DecodeResult holding the decoded text, the accepted non-canonical
+/// placeholders and the rejected ones.
+#[wasm_bindgen]
+pub fn decode_leniently(text: String, mapping: &FieldMapping) -> DecodeResult {
+    text_processor::decode_leniently(text, mapping)
+}
+
+/// Decodes text using the mapping returned by a masking pass.
+///
+/// Restores variant placeholders to the exact text they replaced and
+/// decodes all other field placeholders like `decode_obfuscated_text`.
+///
+/// # Parameters
+///
+/// * `text` - The obfuscated text to decode
+/// * `mapping` - The FieldMapping returned alongside the masked text
+///
+/// # Returns
+///
+/// A String with field placeholders replaced with their original text.
+#[wasm_bindgen]
+pub fn decode_with_mapping(text: String, mapping: &FieldMapping) -> String {
+    text_processor::decode_with_mapping(text, mapping)
+}
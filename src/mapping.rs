@@ -0,0 +1,455 @@
+//! Mapping between field placeholders and the text they replaced.
+
+use wasm_bindgen::prelude::*;
+
+use crate::case_utils::Locale;
+use crate::placeholder::PlaceholderFormat;
+use crate::word_list::MaskEntry;
+
+/// Records what every placeholder emitted by a masking pass stands for.
+///
+/// Field numbers map to the mask word as listed, which is enough to decode
+/// the lowercase, `_F` and `_A` case variants, or the exact word for
+/// case-sensitive entries. Occurrences whose surface form cannot be
+/// reconstructed from the listed word are given their own variant placeholder
+/// and recorded verbatim. The locale and placeholder format used for masking
+/// are kept so that placeholders decode the same way.
+///
+/// With unlinkable placeholders every occurrence is its own field, and each
+/// field after a value's first is linked to that first field.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default)]
+pub struct FieldMapping {
+    fields: Vec<MaskEntry>,
+    /// The index of every linked field with the index of its value's first
+    /// field.
+    links: Vec<(usize, usize)>,
+    variants: Vec<(String, String)>,
+    locale: Locale,
+    placeholder: PlaceholderFormat,
+}
+
+#[wasm_bindgen]
+impl FieldMapping {
+    /// Creates an empty mapping.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> FieldMapping {
+        FieldMapping::default()
+    }
+
+    /// Appends the mask word for the next field number.
+    pub fn add_field(&mut self, word: String) {
+        self.fields.push(MaskEntry::new(word));
+    }
+
+    /// Appends a case-sensitive mask word, which always decodes exactly as
+    /// given, for the next field number.
+    pub fn add_case_sensitive_field(&mut self, word: String) {
+        let mut entry = MaskEntry::new(word);
+        entry.case_sensitive = true;
+        self.fields.push(entry);
+    }
+
+    /// Appends a mask word with a category for the next field number, which
+    /// labels its placeholders under a `{category}` template.
+    pub fn add_categorized_field(&mut self, word: String, category: String) {
+        let mut entry = MaskEntry::new(word);
+        entry.category = Some(category).filter(|category| !category.is_empty());
+        self.fields.push(entry);
+    }
+
+    /// Whether the given one-based field number is case-sensitive.
+    pub fn is_case_sensitive(&self, field_number: usize) -> bool {
+        field_number
+            .checked_sub(1)
+            .and_then(|index| self.fields.get(index))
+            .is_some_and(|entry| entry.case_sensitive)
+    }
+
+    /// Records that a field masks the same value as an earlier one, given
+    /// their one-based field numbers.
+    pub fn link_fields(&mut self, field_number: usize, first_field_number: usize) {
+        if first_field_number == 0 || first_field_number >= field_number {
+            return;
+        }
+        let first = self.first_field(first_field_number - 1);
+        self.links.retain(|(index, _)| *index != field_number - 1);
+        self.links.push((field_number - 1, first));
+    }
+
+    /// The one-based numbers of every field that masks the same value as the
+    /// given field, including itself, in order.
+    pub fn linked_fields(&self, field_number: usize) -> Vec<usize> {
+        let Some(index) = field_number
+            .checked_sub(1)
+            .filter(|index| *index < self.fields.len())
+        else {
+            return Vec::new();
+        };
+        let first = self.first_field(index);
+        (0..self.fields.len())
+            .filter(|other| self.first_field(*other) == first)
+            .map(|other| other + 1)
+            .collect()
+    }
+
+    /// Records the exact text behind a variant placeholder.
+    pub fn add_variant(&mut self, placeholder: String, original: String) {
+        match self
+            .variants
+            .iter_mut()
+            .find(|(existing, _)| *existing == placeholder)
+        {
+            Some(entry) => entry.1 = original,
+            None => self.variants.push((placeholder, original)),
+        }
+    }
+
+    /// The locale whose case mappings decode the case variants, if one with
+    /// special rules is set.
+    #[wasm_bindgen(getter)]
+    pub fn locale(&self) -> Option<String> {
+        self.locale.code().map(str::to_string)
+    }
+
+    /// Sets the locale whose case mappings decode the case variants.
+    #[wasm_bindgen(setter)]
+    pub fn set_locale(&mut self, code: Option<String>) {
+        self.locale = code.as_deref().map(Locale::from_code).unwrap_or_default();
+    }
+
+    /// The template the placeholders were written with.
+    #[wasm_bindgen(getter)]
+    pub fn placeholder_template(&self) -> String {
+        self.placeholder.template()
+    }
+
+    /// Sets the template the placeholders were written with.
+    #[wasm_bindgen(setter)]
+    pub fn set_placeholder_template(&mut self, template: Option<String>) {
+        self.placeholder = self.placeholder.with_template(template.as_deref());
+    }
+
+    /// The text that introduces each placeholder modifier.
+    #[wasm_bindgen(getter)]
+    pub fn suffix_separator(&self) -> String {
+        self.placeholder.separator().to_string()
+    }
+
+    /// Sets the text that introduces each placeholder modifier.
+    #[wasm_bindgen(setter)]
+    pub fn set_suffix_separator(&mut self, separator: Option<String>) {
+        self.placeholder = self.placeholder.with_separator(separator.as_deref());
+    }
+
+    /// The session namespace the placeholders were written with, if any.
+    #[wasm_bindgen(getter)]
+    pub fn session_namespace(&self) -> Option<String> {
+        self.placeholder.namespace().map(str::to_string)
+    }
+
+    /// Sets the session namespace the placeholders were written with.
+    #[wasm_bindgen(setter)]
+    pub fn set_session_namespace(&mut self, namespace: Option<String>) {
+        self.placeholder = self.placeholder.with_namespace(namespace.as_deref());
+    }
+
+    /// Whether the placeholders were written with check characters.
+    #[wasm_bindgen(getter)]
+    pub fn check_characters(&self) -> bool {
+        self.placeholder.is_checked()
+    }
+
+    /// Sets whether the placeholders were written with check characters.
+    #[wasm_bindgen(setter)]
+    pub fn set_check_characters(&mut self, checked: bool) {
+        self.placeholder = self.placeholder.with_check_characters(checked);
+    }
+
+    /// The mask words in field number order.
+    pub fn fields(&self) -> Vec<String> {
+        self.fields.iter().map(|entry| entry.word.clone()).collect()
+    }
+
+    /// The variant placeholders in the order they were first emitted.
+    pub fn variant_placeholders(&self) -> Vec<String> {
+        self.variants
+            .iter()
+            .map(|(placeholder, _)| placeholder.clone())
+            .collect()
+    }
+
+    /// Returns the exact text recorded for a variant placeholder.
+    pub fn variant(&self, placeholder: &str) -> Option<String> {
+        self.variant_text(placeholder).map(str::to_string)
+    }
+}
+
+impl FieldMapping {
+    pub(crate) fn field_entries(&self) -> &[MaskEntry] {
+        &self.fields
+    }
+
+    pub(crate) fn case_locale(&self) -> Locale {
+        self.locale
+    }
+
+    pub(crate) fn set_case_locale(&mut self, locale: Locale) {
+        self.locale = locale;
+    }
+
+    pub(crate) fn placeholder_format(&self) -> &PlaceholderFormat {
+        &self.placeholder
+    }
+
+    pub(crate) fn set_placeholder_format(&mut self, format: PlaceholderFormat) {
+        self.placeholder = format;
+    }
+
+    pub(crate) fn push_field(&mut self, entry: &MaskEntry) {
+        let mut field = MaskEntry::new(entry.word.clone());
+        field.case_sensitive = entry.case_sensitive;
+        field.category = entry.category.clone();
+        self.fields.push(field);
+    }
+
+    /// The index of the first field of the value masked by field `index`.
+    fn first_field(&self, index: usize) -> usize {
+        self.links
+            .iter()
+            .find(|(linked, _)| *linked == index)
+            .map_or(index, |(_, first)| *first)
+    }
+
+    pub(crate) fn variants(&self) -> &[(String, String)] {
+        &self.variants
+    }
+
+    pub(crate) fn variant_text(&self, placeholder: &str) -> Option<&str> {
+        self.variants
+            .iter()
+            .find(|(existing, _)| existing == placeholder)
+            .map(|(_, original)| original.as_str())
+    }
+}
+
+/// Records the pseudonyms chosen during a session and the text they replaced.
+///
+/// Passing the same mapping to every pseudonym masking call keeps each
+/// value's pseudonym stable across calls and never gives two values the same
+/// pseudonym. The key and seed vary which pseudonyms are chosen.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default)]
+pub struct PseudonymMapping {
+    key: String,
+    seed: u32,
+    /// The pseudonym chosen for every value, keyed by the lowercase value.
+    assigned: Vec<(String, String)>,
+    /// Every pseudonym as written in masked text, with the text it replaced.
+    replacements: Vec<(String, String)>,
+}
+
+#[wasm_bindgen]
+impl PseudonymMapping {
+    /// Creates an empty mapping.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> PseudonymMapping {
+        PseudonymMapping::default()
+    }
+
+    /// The seed pseudonyms are chosen with.
+    #[wasm_bindgen(getter)]
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Sets the seed pseudonyms are chosen with. Values that already have a
+    /// pseudonym keep it.
+    #[wasm_bindgen(setter)]
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = seed;
+    }
+
+    /// Sets the key pseudonyms are chosen with, such as a team secret, so
+    /// that sessions with the same key choose the same pseudonyms. Values
+    /// that already have a pseudonym keep it.
+    pub fn set_key(&mut self, key: String) {
+        self.key = key;
+    }
+
+    /// Records the exact text behind a pseudonym as written in masked text.
+    pub fn add_replacement(&mut self, pseudonym: String, original: String) {
+        if self.original_text(&pseudonym).is_none() {
+            self.replacements.push((pseudonym, original));
+        }
+    }
+
+    /// The pseudonyms as written in masked text, in the order they were
+    /// first emitted.
+    pub fn pseudonyms(&self) -> Vec<String> {
+        self.replacements
+            .iter()
+            .map(|(pseudonym, _)| pseudonym.clone())
+            .collect()
+    }
+
+    /// Returns the text a pseudonym replaced.
+    pub fn original(&self, pseudonym: &str) -> Option<String> {
+        self.original_text(pseudonym).map(str::to_string)
+    }
+}
+
+impl PseudonymMapping {
+    pub(crate) fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn assigned(&self, key: &str) -> Option<&str> {
+        self.assigned
+            .iter()
+            .find(|(value, _)| value == key)
+            .map(|(_, pseudonym)| pseudonym.as_str())
+    }
+
+    pub(crate) fn assign(&mut self, key: String, pseudonym: String) {
+        self.assigned.push((key, pseudonym));
+    }
+
+    /// Whether some value was given a pseudonym that lowercases to
+    /// `lowercase`.
+    pub(crate) fn is_taken(&self, lowercase: &str) -> bool {
+        self.assigned
+            .iter()
+            .any(|(_, pseudonym)| pseudonym.to_lowercase() == lowercase)
+    }
+
+    pub(crate) fn replacements(&self) -> &[(String, String)] {
+        &self.replacements
+    }
+
+    fn original_text(&self, pseudonym: &str) -> Option<&str> {
+        self.replacements
+            .iter()
+            .find(|(existing, _)| existing == pseudonym)
+            .map(|(_, original)| original.as_str())
+    }
+}
+
+/// The output of a field masking pass together with its mapping.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct MaskResult {
+    text: String,
+    mapping: FieldMapping,
+}
+
+#[wasm_bindgen]
+impl MaskResult {
+    /// The masked text.
+    #[wasm_bindgen(getter)]
+    pub fn text(&self) -> String {
+        self.text.clone()
+    }
+
+    /// The mapping needed to decode the masked text.
+    #[wasm_bindgen(getter)]
+    pub fn mapping(&self) -> FieldMapping {
+        self.mapping.clone()
+    }
+}
+
+impl MaskResult {
+    pub(crate) fn new(text: String, mapping: FieldMapping) -> Self {
+        MaskResult { text, mapping }
+    }
+}
+
+/// The output of a decoding pass together with the placeholders it could
+/// not decode and, for lenient decoding, the mangled ones it accepted.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct DecodeResult {
+    text: String,
+    rejected: Vec<String>,
+    lenient_matches: Vec<LenientMatch>,
+}
+
+#[wasm_bindgen]
+impl DecodeResult {
+    /// The decoded text.
+    #[wasm_bindgen(getter)]
+    pub fn text(&self) -> String {
+        self.text.clone()
+    }
+
+    /// The placeholders left undecoded because their check character did
+    /// not match their number, in text order.
+    #[wasm_bindgen(getter)]
+    pub fn rejected(&self) -> Vec<String> {
+        self.rejected.clone()
+    }
+
+    /// The mangled placeholders accepted by lenient decoding, in text order.
+    #[wasm_bindgen(getter)]
+    pub fn lenient_matches(&self) -> Vec<LenientMatch> {
+        self.lenient_matches.clone()
+    }
+}
+
+impl DecodeResult {
+    pub(crate) fn new(
+        text: String,
+        rejected: Vec<String>,
+        lenient_matches: Vec<LenientMatch>,
+    ) -> Self {
+        DecodeResult {
+            text,
+            rejected,
+            lenient_matches,
+        }
+    }
+
+    pub(crate) fn into_text(self) -> String {
+        self.text
+    }
+}
+
+/// A placeholder that lenient decoding accepted in a non-canonical form.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct LenientMatch {
+    found: String,
+    canonical: String,
+    decoded: String,
+}
+
+#[wasm_bindgen]
+impl LenientMatch {
+    /// The placeholder as written in the text, such as `Field_3` or `FIELD 3`.
+    #[wasm_bindgen(getter)]
+    pub fn found(&self) -> String {
+        self.found.clone()
+    }
+
+    /// The canonical placeholder it was read as, such as `FIELD_3`.
+    #[wasm_bindgen(getter)]
+    pub fn canonical(&self) -> String {
+        self.canonical.clone()
+    }
+
+    /// The text it was decoded to.
+    #[wasm_bindgen(getter)]
+    pub fn decoded(&self) -> String {
+        self.decoded.clone()
+    }
+}
+
+impl LenientMatch {
+    pub(crate) fn new(found: String, canonical: String, decoded: String) -> Self {
+        LenientMatch {
+            found,
+            canonical,
+            decoded,
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! How hidden text is written when masking with asterisks.
+//!
+//! By default every byte of a value becomes one `*`, which keeps ASCII
+//! layouts intact but leaks the length of each value. The mask can instead
+//! count user-perceived characters, or have a fixed length that hides the
+//! value's length. The glyph can be any character, such as `█` or `•`, or a
+//! longer marker such as `[REDACTED]`, which replaces the hidden text once.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// How long the mask for a value is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MaskLength {
+    /// One glyph per byte of the value.
+    #[default]
+    Bytes,
+    /// One glyph per user-perceived character of the value.
+    Graphemes,
+    /// The same number of glyphs for every value.
+    Fixed,
+}
+
+impl MaskLength {
+    /// Parses a mask length name: `"bytes"`, `"graphemes"` or `"fixed"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "bytes" => Some(MaskLength::Bytes),
+            "graphemes" => Some(MaskLength::Graphemes),
+            "fixed" => Some(MaskLength::Fixed),
+            _ => None,
+        }
+    }
+
+    /// Returns the name accepted by `from_name`.
+    pub fn name(self) -> &'static str {
+        match self {
+            MaskLength::Bytes => "bytes",
+            MaskLength::Graphemes => "graphemes",
+            MaskLength::Fixed => "fixed",
+        }
+    }
+}
+
+/// The glyph and length hidden text is written with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MaskStyle {
+    pub length: MaskLength,
+    pub fixed_length: usize,
+    pub glyph: String,
+}
+
+impl Default for MaskStyle {
+    fn default() -> Self {
+        MaskStyle {
+            length: MaskLength::Bytes,
+            fixed_length: 8,
+            glyph: "*".to_string(),
+        }
+    }
+}
+
+impl MaskStyle {
+    /// Writes the mask for `hidden`, a value or a hidden run of one.
+    pub(crate) fn hide(&self, hidden: &str) -> String {
+        if self.glyph.graphemes(true).nth(1).is_some() {
+            return self.glyph.clone();
+        }
+
+        let count = match self.length {
+            MaskLength::Bytes => hidden.len(),
+            MaskLength::Graphemes => hidden.graphemes(true).count(),
+            MaskLength::Fixed => self.fixed_length,
+        };
+        self.glyph.repeat(count)
+    }
+}
@@ -0,0 +1,517 @@
+//! Locating occurrences of mask words in text.
+//!
+//! Every mask word and alias is compiled into its own capture group of a
+//! single alternation, so each match can be traced back to the entry that
+//! produced it. Allowlisted terms are located in the same pass and veto any
+//! match that lies inside one of them. With segmentation enabled, matches
+//! that cut through a token of a script written without spaces are skipped.
+//! Overlaps between the remaining matches and the values selected by context
+//! rules are resolved by the overlap policy.
+//!
+//! When hyphenated words are joined, line-end hyphens between two letters or
+//! digits are taken out of the text before matching, and the matches are
+//! mapped back onto the original text, breaks included.
+
+use regex::{Regex, RegexBuilder};
+use std::collections::{HashMap, HashSet};
+
+use crate::case_utils::Locale;
+use crate::context_rules::{find_context_spans, ContextRule};
+use crate::inflection::inflected_pattern;
+use crate::options::MaskOptions;
+use crate::overlap::{resolve_overlaps, Candidate, OverlapPolicy};
+use crate::segmentation::TokenBoundaries;
+use crate::word_list::{MaskEntry, NamePart};
+
+/// A single occurrence of a mask word in the input text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct WordMatch {
+    pub start: usize,
+    pub end: usize,
+    /// Index of the matching entry in the slice the matcher was built from.
+    ///
+    /// Indexes past the end of that slice refer to distinct context rule
+    /// values, numbered in order of first appearance.
+    pub word_index: usize,
+    /// One-based index of the alias that matched, if it was not the word itself.
+    pub alias: Option<usize>,
+    /// The part of a multi-word entry that matched on its own, if any.
+    pub part: Option<NamePart>,
+    /// The context rule that selected the value, for context rule values.
+    pub rule: Option<usize>,
+}
+
+/// A single string to search for and the entry it belongs to.
+struct MatchTerm<'a> {
+    text: &'a str,
+    word_index: usize,
+    alias: Option<usize>,
+    part: Option<NamePart>,
+    case_sensitive: bool,
+    /// Whether the term only matches as a whole word, uninflected.
+    whole_word: bool,
+}
+
+pub(crate) struct WordMatcher {
+    regex: Option<Regex>,
+    terms: Vec<(usize, Option<usize>, Option<NamePart>)>,
+    allowlist: Option<Regex>,
+    context_rules: Vec<ContextRule>,
+    entry_count: usize,
+    segmentation: bool,
+    join_hyphenated: bool,
+    policy: OverlapPolicy,
+    merge_overlaps: bool,
+    /// The word list position and category rank of every entry.
+    entry_ranks: Vec<(usize, usize)>,
+    /// The category rank of every context rule.
+    rule_ranks: Vec<usize>,
+}
+
+impl WordMatcher {
+    /// Compiles the given entries, in priority order, into a matcher.
+    ///
+    /// Words and aliases are tried in the order the overlap policy prefers
+    /// them, longest first by default, so an alias that contains another
+    /// entry's word still wins at the same position.
+    ///
+    /// Returns `None` when there is nothing to match or the pattern fails to
+    /// compile.
+    pub(crate) fn new(entries: &[MaskEntry], options: &MaskOptions) -> Option<Self> {
+        if entries.is_empty() && options.context_rules().is_empty() {
+            return None;
+        }
+
+        let entry_ranks: Vec<(usize, usize)> = entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.list_index,
+                    options.category_rank(entry.category.as_deref()),
+                )
+            })
+            .collect();
+
+        let mut terms: Vec<MatchTerm> = Vec::new();
+        for (word_index, entry) in entries.iter().enumerate() {
+            terms.push(MatchTerm {
+                text: &entry.word,
+                word_index,
+                alias: None,
+                part: None,
+                case_sensitive: entry.case_sensitive,
+                whole_word: false,
+            });
+            for (alias_index, alias) in entry.aliases.iter().enumerate() {
+                let exact = entry.exact_aliases.contains(&(alias_index + 1));
+                terms.push(MatchTerm {
+                    text: alias,
+                    word_index,
+                    alias: Some(alias_index + 1),
+                    part: None,
+                    case_sensitive: entry.case_sensitive || exact,
+                    whole_word: exact,
+                });
+            }
+        }
+        if options.mask_name_parts() {
+            terms.extend(name_part_terms(entries));
+        }
+        terms.sort_by_key(|term| {
+            let (list_index, priority) = entry_ranks[term.word_index];
+            let length_rank = usize::MAX - term.text.len();
+            match options.policy() {
+                OverlapPolicy::Leftmost | OverlapPolicy::Longest => (0, length_rank),
+                OverlapPolicy::FirstListed => (list_index, 0),
+                OverlapPolicy::Priority => (priority, length_rank),
+            }
+        });
+
+        let regex = if terms.is_empty() {
+            None
+        } else {
+            Some(build_case_insensitive_regex(&terms, options)?)
+        };
+        let allowlist = build_allowlist_regex(options.allowlist());
+        Some(WordMatcher {
+            regex,
+            terms: terms
+                .iter()
+                .map(|term| (term.word_index, term.alias, term.part))
+                .collect(),
+            allowlist,
+            context_rules: options.context_rules().to_vec(),
+            entry_count: entries.len(),
+            segmentation: options.uses_segmentation(),
+            join_hyphenated: options.join_hyphenated(),
+            policy: options.policy(),
+            merge_overlaps: options.merges_overlaps(),
+            entry_ranks,
+            rule_ranks: options
+                .context_rules()
+                .iter()
+                .map(|rule| options.category_rank(rule.category.as_deref()))
+                .collect(),
+        })
+    }
+
+    /// Finds the occurrences to mask, sorted by start.
+    ///
+    /// Every position where a word matches yields one candidate, the word the
+    /// overlap policy prefers at that position. Candidates inside an
+    /// allowlisted term or that split a token are dropped, and the remaining
+    /// overlaps, including those with context rule values, are resolved by
+    /// the policy.
+    pub(crate) fn find_matches(&self, text: &str) -> Vec<WordMatch> {
+        if !self.join_hyphenated {
+            return self.find_matches_in(text);
+        }
+
+        let joined = JoinedText::new(text);
+        let mut matches = self.find_matches_in(&joined.text);
+        for word_match in &mut matches {
+            word_match.start = joined.original_start(word_match.start);
+            word_match.end = joined.original_end(word_match.end);
+        }
+        matches
+    }
+
+    fn find_matches_in(&self, text: &str) -> Vec<WordMatch> {
+        let allowed_spans = self.allowed_spans(text);
+        let token_boundaries = if self.segmentation {
+            TokenBoundaries::find(text)
+        } else {
+            None
+        };
+        let mut candidates = self.context_matches(text);
+        let mut cursor = 0;
+
+        while cursor <= text.len() {
+            let Some(word_match) = self.match_at(text, cursor) else {
+                break;
+            };
+            cursor = next_char_boundary(text, word_match.start);
+
+            let is_allowed = allowed_spans
+                .iter()
+                .any(|&(start, end)| start <= word_match.start && word_match.end <= end);
+            let splits_token = token_boundaries.as_ref().is_some_and(|boundaries| {
+                boundaries.splits_token(text, word_match.start, word_match.end)
+            });
+
+            if !is_allowed && !splits_token {
+                let (list_index, priority) = self.entry_ranks[word_match.word_index];
+                candidates.push(Candidate {
+                    word_match,
+                    list_index,
+                    priority,
+                    is_context_value: false,
+                });
+            }
+        }
+
+        let mut matches = resolve_overlaps(candidates, self.policy, self.merge_overlaps);
+        self.renumber_context_values(&mut matches);
+        matches
+    }
+
+    /// Numbers the context values that survived overlap resolution right
+    /// after the entries, in the order they first appear.
+    fn renumber_context_values(&self, matches: &mut [WordMatch]) {
+        let mut value_indexes: HashMap<usize, usize> = HashMap::new();
+        for word_match in matches
+            .iter_mut()
+            .filter(|word_match| word_match.word_index >= self.entry_count)
+        {
+            let next_index = self.entry_count + value_indexes.len();
+            word_match.word_index = *value_indexes
+                .entry(word_match.word_index)
+                .or_insert(next_index);
+        }
+    }
+
+    fn context_matches(&self, text: &str) -> Vec<Candidate> {
+        let mut value_indexes: HashMap<&str, usize> = HashMap::new();
+
+        find_context_spans(text, &self.context_rules)
+            .into_iter()
+            .map(|(start, end, rule_index)| {
+                let next_index = self.entry_count + value_indexes.len();
+                let word_index = *value_indexes.entry(&text[start..end]).or_insert(next_index);
+                Candidate {
+                    word_match: WordMatch {
+                        start,
+                        end,
+                        word_index,
+                        alias: None,
+                        part: None,
+                        rule: Some(rule_index),
+                    },
+                    list_index: usize::MAX,
+                    priority: self.rule_ranks[rule_index],
+                    is_context_value: true,
+                }
+            })
+            .collect()
+    }
+
+    fn match_at(&self, text: &str, start: usize) -> Option<WordMatch> {
+        let captures = self.regex.as_ref()?.captures_at(text, start)?;
+        captures
+            .iter()
+            .enumerate()
+            .skip(1)
+            .find_map(|(group, matched)| {
+                let (word_index, alias, part) = self.terms[group - 1];
+                matched.map(|matched| WordMatch {
+                    start: matched.start(),
+                    end: matched.end(),
+                    word_index,
+                    alias,
+                    part,
+                    rule: None,
+                })
+            })
+    }
+
+    /// Returns every occurrence of an allowlisted term, including overlapping
+    /// ones.
+    fn allowed_spans(&self, text: &str) -> Vec<(usize, usize)> {
+        let Some(allowlist) = &self.allowlist else {
+            return Vec::new();
+        };
+
+        let mut spans = Vec::new();
+        let mut cursor = 0;
+        while let Some(allowed) = allowlist.find_at(text, cursor) {
+            spans.push((allowed.start(), allowed.end()));
+            cursor = next_char_boundary(text, allowed.start());
+        }
+        spans
+    }
+}
+
+/// Returns a term for the first and last word of every multi-word entry,
+/// skipping parts that are ambiguous or already listed as a word or alias.
+fn name_part_terms(entries: &[MaskEntry]) -> Vec<MatchTerm<'_>> {
+    let listed_keys: HashSet<String> = entries
+        .iter()
+        .flat_map(|entry| {
+            std::iter::once(&entry.word)
+                .chain(&entry.aliases)
+                .map(move |term| entry.dedupe_key(term))
+        })
+        .collect();
+
+    let mut parts: Vec<MatchTerm> = Vec::new();
+    let mut part_keys: HashMap<String, usize> = HashMap::new();
+    for (word_index, entry) in entries.iter().enumerate() {
+        for part in NamePart::ALL {
+            let Some(text) = part.of(&entry.word) else {
+                continue;
+            };
+            let key = entry.dedupe_key(text);
+            if listed_keys.contains(&key) {
+                continue;
+            }
+
+            // The same text as the first and the last part counts once.
+            if parts
+                .iter()
+                .any(|term| term.word_index == word_index && term.text == text)
+            {
+                continue;
+            }
+            *part_keys.entry(key).or_insert(0) += 1;
+            parts.push(MatchTerm {
+                text,
+                word_index,
+                alias: None,
+                part: Some(part),
+                case_sensitive: entry.case_sensitive,
+                whole_word: false,
+            });
+        }
+    }
+
+    parts.retain(|term| {
+        let key = entries[term.word_index].dedupe_key(term.text);
+        part_keys.get(&key) == Some(&1)
+    });
+    parts
+}
+
+/// Text with its line-end hyphenation taken out, as `Hold-\n  ings` becomes
+/// `Holdings`.
+struct JoinedText {
+    text: String,
+    /// The position in the joined text of every break taken out, with its
+    /// length.
+    breaks: Vec<(usize, usize)>,
+}
+
+impl JoinedText {
+    fn new(original: &str) -> Self {
+        let mut text = String::with_capacity(original.len());
+        let mut breaks = Vec::new();
+        let mut cursor = 0;
+
+        for (hyphen, _) in original.match_indices('-') {
+            if hyphen < cursor
+                || !original[..hyphen]
+                    .chars()
+                    .next_back()
+                    .is_some_and(char::is_alphanumeric)
+            {
+                continue;
+            }
+            let Some(break_len) = line_end_hyphen_len(&original[hyphen..]) else {
+                continue;
+            };
+
+            text.push_str(&original[cursor..hyphen]);
+            breaks.push((text.len(), break_len));
+            cursor = hyphen + break_len;
+        }
+        text.push_str(&original[cursor..]);
+
+        JoinedText { text, breaks }
+    }
+
+    /// The original position of the character at `position`.
+    fn original_start(&self, position: usize) -> usize {
+        position + self.removed_before(|at| at <= position)
+    }
+
+    /// The original position right after the character before `position`,
+    /// leaving out a break that ends there.
+    fn original_end(&self, position: usize) -> usize {
+        position + self.removed_before(|at| at < position)
+    }
+
+    fn removed_before(&self, is_before: impl Fn(usize) -> bool) -> usize {
+        self.breaks
+            .iter()
+            .take_while(|(at, _)| is_before(*at))
+            .map(|(_, len)| len)
+            .sum()
+    }
+}
+
+/// Returns the length of a hyphen that ends a line at the start of `text`,
+/// with the indentation of the next line, when a letter or digit follows.
+fn line_end_hyphen_len(text: &str) -> Option<usize> {
+    let rest = text.strip_prefix('-')?;
+    let rest = rest.trim_start_matches(|c: char| c.is_whitespace() && c != '\r' && c != '\n');
+    let rest = rest.strip_prefix('\r').unwrap_or(rest);
+    let rest = rest.strip_prefix('\n')?.trim_start();
+    rest.starts_with(char::is_alphanumeric)
+        .then_some(text.len() - rest.len())
+}
+
+fn next_char_boundary(text: &str, index: usize) -> usize {
+    index
+        + text[index..]
+            .chars()
+            .next()
+            .map_or(1, |character| character.len_utf8())
+}
+
+/// How the literal text of a term is turned into a regex fragment.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TermSyntax {
+    locale: Locale,
+    flexible_whitespace: bool,
+}
+
+impl TermSyntax {
+    /// Escapes `text`, replacing runs of whitespace with `\s+` when
+    /// whitespace is flexible.
+    pub(crate) fn escape(self, text: &str) -> String {
+        if !self.flexible_whitespace {
+            return self.locale.escape_pattern(text);
+        }
+
+        let mut pattern = String::with_capacity(text.len() * 2);
+        let mut rest = text;
+
+        while !rest.is_empty() {
+            let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            pattern.push_str(&self.locale.escape_pattern(&rest[..word_end]));
+            rest = &rest[word_end..];
+
+            let space_end = rest
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(rest.len());
+            if space_end > 0 {
+                pattern.push_str(r"\s+");
+            }
+            rest = &rest[space_end..];
+        }
+
+        pattern
+    }
+}
+
+fn word_pattern(term: &MatchTerm, options: &MaskOptions) -> String {
+    // Case-sensitive terms match their exact spelling, so the locale's case
+    // folding does not apply to them.
+    let syntax = TermSyntax {
+        locale: if term.case_sensitive {
+            Locale::Neutral
+        } else {
+            options.case_locale()
+        },
+        flexible_whitespace: options.flexible_whitespace(),
+    };
+    let pattern = match options.language() {
+        _ if term.whole_word => format!(r"\b{}\b", syntax.escape(term.text)),
+        Some(language) => inflected_pattern(term.text, language, syntax),
+        None => syntax.escape(term.text),
+    };
+
+    if term.case_sensitive {
+        format!("(?-i:{})", pattern)
+    } else {
+        pattern
+    }
+}
+
+/// Compiles the terms case-insensitively, except for case-sensitive entries
+/// whose groups opt out with an inline `(?-i:...)` flag.
+fn build_case_insensitive_regex(terms: &[MatchTerm], options: &MaskOptions) -> Option<Regex> {
+    if terms.is_empty() {
+        return None;
+    }
+
+    let pattern = terms
+        .iter()
+        .map(|term| format!("({})", word_pattern(term, options)))
+        .collect::<Vec<_>>()
+        .join("|");
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(true)
+        .build()
+        .ok()
+}
+
+fn build_allowlist_regex(allowlist: &[String]) -> Option<Regex> {
+    if allowlist.is_empty() {
+        return None;
+    }
+
+    let mut terms: Vec<&String> = allowlist.iter().collect();
+    terms.sort_by_key(|term| std::cmp::Reverse(term.len()));
+
+    let pattern = terms
+        .iter()
+        .map(|term| regex::escape(term))
+        .collect::<Vec<_>>()
+        .join("|");
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(true)
+        .build()
+        .ok()
+}
@@ -0,0 +1,528 @@
+//! Configuration shared by the masking and decoding entry points.
+
+use js_sys::{Array, Set};
+use wasm_bindgen::prelude::*;
+
+use crate::case_utils::Locale;
+use crate::context_rules::{ContextRule, ContextValue};
+use crate::inflection::Language;
+use crate::mask_style::{MaskLength, MaskStyle};
+use crate::overlap::OverlapPolicy;
+use crate::placeholder::PlaceholderFormat;
+use crate::reveal::Reveal;
+
+/// Options controlling how mask words are matched and replaced.
+///
+/// A default-constructed value reproduces the behavior of `mask_text`,
+/// `mask_text_with_fields` and `decode_obfuscated_text`.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default)]
+pub struct MaskOptions {
+    inflection_language: Option<Language>,
+    locale: Locale,
+    segmentation: bool,
+    flexible_whitespace: bool,
+    join_hyphenated: bool,
+    mask_name_parts: bool,
+    derive_variants: bool,
+    placeholder: PlaceholderFormat,
+    unlinkable_placeholders: bool,
+    namespace_sessions: bool,
+    allowlist: Vec<String>,
+    context_rules: Vec<ContextRule>,
+    overlap_policy: OverlapPolicy,
+    category_priority: Vec<String>,
+    merge_overlaps: bool,
+    category_reveals: Vec<(String, Reveal)>,
+    mask_style: MaskStyle,
+}
+
+#[wasm_bindgen]
+impl MaskOptions {
+    /// Creates a new set of options with every feature disabled.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> MaskOptions {
+        MaskOptions::default()
+    }
+
+    /// The language used to match inflected forms of mask words, if any.
+    #[wasm_bindgen(getter)]
+    pub fn inflection_language(&self) -> Option<String> {
+        self.inflection_language
+            .map(|language| language.code().to_string())
+    }
+
+    /// Enables matching of plural, possessive and other inflected forms.
+    ///
+    /// Accepts `"en"`, `"de"`, `"fr"` or `"es"`. Any other value disables
+    /// inflection matching.
+    #[wasm_bindgen(setter)]
+    pub fn set_inflection_language(&mut self, code: Option<String>) {
+        self.inflection_language = code.as_deref().and_then(Language::from_code);
+    }
+
+    /// The locale used for case folding, if one with special rules is set.
+    #[wasm_bindgen(getter)]
+    pub fn locale(&self) -> Option<String> {
+        self.locale.code().map(str::to_string)
+    }
+
+    /// Sets the locale whose case mappings are used to match mask words and
+    /// to restore their case when decoding.
+    ///
+    /// `"tr"` and `"az"` pair "i" with "İ" and "ı" with "I", `"el"` ignores
+    /// Greek accents and drops them in uppercase, and `"de"` matches "ß"
+    /// against "ss". Region subtags such as `"de-CH"` are ignored, and any
+    /// other value selects the locale-independent Unicode mappings.
+    #[wasm_bindgen(setter)]
+    pub fn set_locale(&mut self, code: Option<String>) {
+        self.locale = code.as_deref().map(Locale::from_code).unwrap_or_default();
+    }
+
+    /// Whether occurrences must respect token boundaries in scripts written
+    /// without spaces.
+    #[cfg(feature = "segmentation")]
+    #[wasm_bindgen(getter)]
+    pub fn segmentation(&self) -> bool {
+        self.segmentation
+    }
+
+    /// Only masks occurrences that start and end on a word boundary when the
+    /// surrounding text is Chinese, Japanese, Thai, Lao, Khmer or Burmese.
+    ///
+    /// Boundaries come from dictionary-based segmentation, so "京都" is not
+    /// masked inside "東京都". Text in other scripts is matched as before.
+    #[cfg(feature = "segmentation")]
+    #[wasm_bindgen(setter)]
+    pub fn set_segmentation(&mut self, enabled: bool) {
+        self.segmentation = enabled;
+    }
+
+    /// Whether the words of a phrase match across any run of whitespace.
+    #[wasm_bindgen(getter)]
+    pub fn flexible_whitespace(&self) -> bool {
+        self.flexible_whitespace
+    }
+
+    /// Lets the words of a mask phrase be separated by any run of whitespace,
+    /// including line breaks, so "Acme Holdings Ltd" also matches wrapped or
+    /// double-spaced occurrences.
+    ///
+    /// Occurrences whose spacing differs from the listed phrase are recorded
+    /// in the mapping, so `decode_with_mapping` restores their spacing.
+    #[wasm_bindgen(setter)]
+    pub fn set_flexible_whitespace(&mut self, flexible: bool) {
+        self.flexible_whitespace = flexible;
+    }
+
+    /// Whether words hyphenated at a line end still match.
+    #[wasm_bindgen(getter)]
+    pub fn join_hyphenated(&self) -> bool {
+        self.join_hyphenated
+    }
+
+    /// Lets a mask word match when it is split across lines with a hyphen,
+    /// as in "Hold-\nings".
+    #[wasm_bindgen(setter)]
+    pub fn set_join_hyphenated(&mut self, join: bool) {
+        self.join_hyphenated = join;
+    }
+
+    /// Whether the first and last words of multi-word entries are masked on
+    /// their own.
+    #[wasm_bindgen(getter)]
+    pub fn mask_name_parts(&self) -> bool {
+        self.mask_name_parts
+    }
+
+    /// Also masks the first and last words of every multi-word entry, with
+    /// placeholders linked to the entry's field.
+    ///
+    /// With "John Smith" as `FIELD_1`, a later "Smith" becomes
+    /// `FIELD_1_LAST_F` and "John" becomes `FIELD_1_FIRST_F`. A part shared by
+    /// several entries, or equal to another word or alias, is not masked.
+    #[wasm_bindgen(setter)]
+    pub fn set_mask_name_parts(&mut self, mask: bool) {
+        self.mask_name_parts = mask;
+    }
+
+    /// Whether derived forms of every mask word are masked as aliases.
+    #[wasm_bindgen(getter)]
+    pub fn derive_variants(&self) -> bool {
+        self.derive_variants
+    }
+
+    /// Also masks the common derived forms of every mask word with its field
+    /// number: initials such as "J. Doe" and "JD", usernames such as "jdoe",
+    /// "jane.doe", "jane_doe" and "doe.jane", possessives, and the halves of
+    /// a hyphenated surname. Initials such as "JD" only match as whole words
+    /// in capitals, so "JDK" is left alone.
+    ///
+    /// Derived forms are numbered as aliases after the listed ones. Pass the
+    /// same options to `decode_obfuscated_text_with_options` to decode them
+    /// without a mapping.
+    #[wasm_bindgen(setter)]
+    pub fn set_derive_variants(&mut self, derive: bool) {
+        self.derive_variants = derive;
+    }
+
+    /// The template placeholders are written with.
+    #[wasm_bindgen(getter)]
+    pub fn placeholder_template(&self) -> String {
+        self.placeholder.template()
+    }
+
+    /// Sets the template placeholders are written with, such as
+    /// `"[REDACTED_{n}]"`, `"<<{n}>>"` or `"⟦{n}⟧"`, where `{n}` stands for
+    /// the field number followed by its modifiers.
+    ///
+    /// A `{category}` marker before `{n}`, as in `"{category}_{n}"`, labels
+    /// each placeholder with the category of its entry or context rule and
+    /// numbers fields within each category, producing `PERSON_1`, `EMAIL_1`
+    /// and `PERSON_2`. Fields without a category are labeled `FIELD`.
+    ///
+    /// The template needs text right before `{n}`, and that text must not
+    /// end in a digit. Anything else restores `"FIELD_{n}"`. Decoding must
+    /// use the same template.
+    #[wasm_bindgen(setter)]
+    pub fn set_placeholder_template(&mut self, template: Option<String>) {
+        self.placeholder = self.placeholder.with_template(template.as_deref());
+    }
+
+    /// The text that introduces each placeholder modifier.
+    #[wasm_bindgen(getter)]
+    pub fn suffix_separator(&self) -> String {
+        self.placeholder.separator().to_string()
+    }
+
+    /// Sets the text that introduces the alias, name part and case modifiers
+    /// after the field number, so `":"` writes `FIELD_1:V2:F` instead of
+    /// `FIELD_1_V2_F`. An empty value restores `"_"`.
+    #[wasm_bindgen(setter)]
+    pub fn set_suffix_separator(&mut self, separator: Option<String>) {
+        self.placeholder = self.placeholder.with_separator(separator.as_deref());
+    }
+
+    /// The session namespace placeholders are written with, if any.
+    #[wasm_bindgen(getter)]
+    pub fn session_namespace(&self) -> Option<String> {
+        self.placeholder.namespace().map(str::to_string)
+    }
+
+    /// Sets a session namespace that goes before every field number, so
+    /// `"a7"` writes `FIELD_a7_1` instead of `FIELD_1`.
+    ///
+    /// The namespace must be ASCII letters and digits starting with a letter;
+    /// anything else removes it. Decoding must use the same namespace.
+    #[wasm_bindgen(setter)]
+    pub fn set_session_namespace(&mut self, namespace: Option<String>) {
+        self.placeholder = self.placeholder.with_namespace(namespace.as_deref());
+    }
+
+    /// Whether every field number is followed by a check character.
+    #[wasm_bindgen(getter)]
+    pub fn check_characters(&self) -> bool {
+        self.placeholder.is_checked()
+    }
+
+    /// Writes a check character after every field number, as in
+    /// `FIELD_12K_F`, computed from the number.
+    ///
+    /// Decoding with the same options leaves placeholders whose check
+    /// character is missing or wrong undecoded, and `decode_with_details`
+    /// reports them, so an invented `FIELD_7` or a transposed `FIELD_21K`
+    /// never decodes to the wrong word.
+    #[wasm_bindgen(setter)]
+    pub fn set_check_characters(&mut self, checked: bool) {
+        self.placeholder = self.placeholder.with_check_characters(checked);
+    }
+
+    /// Whether each masking pass without a session namespace generates one.
+    #[wasm_bindgen(getter)]
+    pub fn namespace_sessions(&self) -> bool {
+        self.namespace_sessions
+    }
+
+    /// Gives every `mask_text_with_fields_and_mapping` call without a session
+    /// namespace a random one, such as `a7k2`, recorded in its mapping.
+    ///
+    /// Text that mixes several documents masked this way decodes with
+    /// `decode_with_mappings` and all of their mappings.
+    #[wasm_bindgen(setter)]
+    pub fn set_namespace_sessions(&mut self, namespace: bool) {
+        self.namespace_sessions = namespace;
+    }
+
+    /// Whether every occurrence gets its own placeholder.
+    #[wasm_bindgen(getter)]
+    pub fn unlinkable_placeholders(&self) -> bool {
+        self.unlinkable_placeholders
+    }
+
+    /// Gives every occurrence its own field number, so the masked text no
+    /// longer shows which occurrences are the same value. "Alice met Alice"
+    /// becomes "FIELD_1 met FIELD_2" instead of "FIELD_1 met FIELD_1".
+    ///
+    /// The mapping still links the fields of each value, and only
+    /// `decode_with_mapping` can decode such text.
+    #[wasm_bindgen(setter)]
+    pub fn set_unlinkable_placeholders(&mut self, unlinkable: bool) {
+        self.unlinkable_placeholders = unlinkable;
+    }
+
+    /// Sets the terms that must never be masked.
+    ///
+    /// A mask word occurrence is skipped whenever it lies inside an
+    /// occurrence of an allowlisted term, so allowing "Samsung" keeps
+    /// "Sam" from masking part of it. Terms are compared case-insensitively.
+    pub fn set_allowlist(&mut self, allowlist: &Set) {
+        self.allowlist = Array::from(allowlist)
+            .iter()
+            .filter_map(|term| term.as_string())
+            .filter(|term| !term.is_empty())
+            .collect();
+    }
+
+    /// Masks the next `token_count` whitespace-separated tokens after every
+    /// occurrence of `keyword`, e.g. the value after `password:`.
+    ///
+    /// Each distinct value receives its own field number after those of the
+    /// mask words.
+    pub fn add_token_rule(&mut self, keyword: String, token_count: usize) {
+        self.context_rules.push(ContextRule {
+            keyword,
+            value: ContextValue::Tokens(token_count),
+            category: None,
+        });
+    }
+
+    /// Masks everything after every occurrence of `keyword` up to the next
+    /// `delimiter`, or up to the end of the text if there is none.
+    pub fn add_delimiter_rule(&mut self, keyword: String, delimiter: String) {
+        self.context_rules.push(ContextRule {
+            keyword,
+            value: ContextValue::UpTo(delimiter),
+            category: None,
+        });
+    }
+
+    /// Assigns a category to the values of every rule with the given
+    /// keyword, for ranking them under the `"priority"` overlap policy and
+    /// labeling their placeholders under a `{category}` template.
+    pub fn set_rule_category(&mut self, keyword: &str, category: String) {
+        for rule in self
+            .context_rules
+            .iter_mut()
+            .filter(|rule| rule.keyword == keyword)
+        {
+            rule.category = Some(category.clone());
+        }
+    }
+
+    /// The policy that resolves overlapping occurrences.
+    #[wasm_bindgen(getter)]
+    pub fn overlap_policy(&self) -> String {
+        self.overlap_policy.name().to_string()
+    }
+
+    /// Sets how overlapping occurrences of different mask words and context
+    /// rule values are resolved.
+    ///
+    /// * `"leftmost"` (the default) masks the occurrence that starts first,
+    ///   the longest word at the same position, and lets context rule values
+    ///   override words.
+    /// * `"longest"` masks the longest occurrence wherever it starts.
+    /// * `"first-listed"` masks the occurrence of the word listed first.
+    /// * `"priority"` masks the occurrence whose category comes first in the
+    ///   category priority, then the longest one.
+    ///
+    /// Any other value restores the default.
+    #[wasm_bindgen(setter)]
+    pub fn set_overlap_policy(&mut self, name: Option<String>) {
+        self.overlap_policy = name
+            .as_deref()
+            .and_then(OverlapPolicy::from_name)
+            .unwrap_or_default();
+    }
+
+    /// Sets the categories ranked by the `"priority"` overlap policy, highest
+    /// priority first, e.g. `["secret", "name"]`.
+    ///
+    /// Words and rules without a listed category rank below all listed ones.
+    pub fn set_category_priority(&mut self, categories: &Array) {
+        self.category_priority = categories
+            .iter()
+            .filter_map(|category| category.as_string())
+            .collect();
+    }
+
+    /// Whether overlapping occurrences are masked as one merged span.
+    #[wasm_bindgen(getter)]
+    pub fn merge_overlaps(&self) -> bool {
+        self.merge_overlaps
+    }
+
+    /// Masks every group of overlapping or nested occurrences as a single
+    /// span instead of dropping the losers.
+    ///
+    /// The span takes the field number of the occurrence the overlap policy
+    /// prefers, and the mapping records its exact text.
+    #[wasm_bindgen(setter)]
+    pub fn set_merge_overlaps(&mut self, merge: bool) {
+        self.merge_overlaps = merge;
+    }
+
+    /// How the length of asterisk masks is chosen.
+    #[wasm_bindgen(getter)]
+    pub fn mask_length(&self) -> String {
+        self.mask_style.length.name().to_string()
+    }
+
+    /// Sets how the length of asterisk masks is chosen:
+    ///
+    /// * `"bytes"` writes one glyph per byte of the value, the default
+    /// * `"graphemes"` writes one glyph per user-perceived character, so
+    ///   "José" becomes four glyphs and layouts are preserved
+    /// * `"fixed"` writes `fixed_mask_length` glyphs for every value, which
+    ///   hides how long each value is
+    ///
+    /// Any other value restores the default.
+    #[wasm_bindgen(setter)]
+    pub fn set_mask_length(&mut self, name: Option<String>) {
+        self.mask_style.length = name
+            .as_deref()
+            .and_then(MaskLength::from_name)
+            .unwrap_or_default();
+    }
+
+    /// The number of glyphs of every mask under the `"fixed"` mask length.
+    #[wasm_bindgen(getter)]
+    pub fn fixed_mask_length(&self) -> usize {
+        self.mask_style.fixed_length
+    }
+
+    /// Sets the number of glyphs of every mask under the `"fixed"` mask
+    /// length, at least one.
+    #[wasm_bindgen(setter)]
+    pub fn set_fixed_mask_length(&mut self, length: usize) {
+        self.mask_style.fixed_length = length.max(1);
+    }
+
+    /// The text asterisk masks are written with.
+    #[wasm_bindgen(getter)]
+    pub fn mask_glyph(&self) -> String {
+        self.mask_style.glyph.clone()
+    }
+
+    /// Sets the text asterisk masks are written with. A single character
+    /// such as `"█"` or `"•"` is repeated to the mask length, while a longer
+    /// marker such as `"[REDACTED]"` replaces each hidden value once,
+    /// whatever the mask length. An empty value restores `"*"`.
+    #[wasm_bindgen(setter)]
+    pub fn set_mask_glyph(&mut self, glyph: Option<String>) {
+        self.mask_style.glyph = glyph
+            .filter(|glyph| !glyph.is_empty())
+            .unwrap_or_else(|| "*".to_string());
+    }
+
+    /// Leaves some characters of every value in a category visible when
+    /// masking with asterisks, unless its entry sets its own.
+    ///
+    /// # Parameters
+    ///
+    /// * `category` - The category of the entries and context rules
+    /// * `keep_first` - How many leading characters stay visible
+    /// * `keep_last` - How many trailing characters stay visible
+    /// * `keep_domain` - Whether the domain of an email address stays visible
+    /// * `keep_country_code` - Whether the `+` and country code of a phone
+    ///   number stay visible
+    pub fn set_category_reveal(
+        &mut self,
+        category: String,
+        keep_first: usize,
+        keep_last: usize,
+        keep_domain: bool,
+        keep_country_code: bool,
+    ) {
+        let reveal = Reveal {
+            keep_first,
+            keep_last,
+            keep_domain,
+            keep_country_code,
+        };
+        match self
+            .category_reveals
+            .iter_mut()
+            .find(|(listed, _)| *listed == category)
+        {
+            Some(entry) => entry.1 = reveal,
+            None => self.category_reveals.push((category, reveal)),
+        }
+    }
+}
+
+impl MaskOptions {
+    pub(crate) fn language(&self) -> Option<Language> {
+        self.inflection_language
+    }
+
+    pub(crate) fn case_locale(&self) -> Locale {
+        self.locale
+    }
+
+    pub(crate) fn uses_segmentation(&self) -> bool {
+        self.segmentation
+    }
+
+    pub(crate) fn placeholder_format(&self) -> &PlaceholderFormat {
+        &self.placeholder
+    }
+
+    pub(crate) fn namespaces_sessions(&self) -> bool {
+        self.namespace_sessions
+    }
+
+    pub(crate) fn unlinks_placeholders(&self) -> bool {
+        self.unlinkable_placeholders
+    }
+
+    pub(crate) fn allowlist(&self) -> &[String] {
+        &self.allowlist
+    }
+
+    pub(crate) fn context_rules(&self) -> &[ContextRule] {
+        &self.context_rules
+    }
+
+    pub(crate) fn policy(&self) -> OverlapPolicy {
+        self.overlap_policy
+    }
+
+    /// The rank of a category under the priority policy; lower wins.
+    pub(crate) fn category_rank(&self, category: Option<&str>) -> usize {
+        category
+            .and_then(|category| {
+                self.category_priority
+                    .iter()
+                    .position(|listed| listed == category)
+            })
+            .unwrap_or(self.category_priority.len())
+    }
+
+    pub(crate) fn merges_overlaps(&self) -> bool {
+        self.merge_overlaps
+    }
+
+    pub(crate) fn mask_style(&self) -> &MaskStyle {
+        &self.mask_style
+    }
+
+    /// What values of a category leave visible, if the category sets it.
+    pub(crate) fn category_reveal(&self, category: Option<&str>) -> Option<Reveal> {
+        let category = category?;
+        self.category_reveals
+            .iter()
+            .find(|(listed, _)| listed == category)
+            .map(|(_, reveal)| *reveal)
+    }
+}
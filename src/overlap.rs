@@ -0,0 +1,139 @@
+//! Resolution of overlapping occurrences.
+//!
+//! The matcher collects at most one candidate per start position, then the
+//! overlap policy decides which of the overlapping candidates are kept.
+//! Alternatively, every group of overlapping candidates can be merged into a
+//! single span attributed to the candidate the policy prefers.
+
+use crate::matcher::WordMatch;
+
+/// How overlapping occurrences of different mask words are resolved.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverlapPolicy {
+    /// The occurrence that starts first wins, and the longest word wins at
+    /// the same position. Context rule values win over any word.
+    #[default]
+    Leftmost,
+    /// The longest occurrence wins wherever it starts.
+    Longest,
+    /// The occurrence of the word listed first wins.
+    FirstListed,
+    /// The occurrence whose category comes first in the priority list wins,
+    /// then the longest one.
+    Priority,
+}
+
+impl OverlapPolicy {
+    /// Parses a policy name: `"leftmost"`, `"longest"`, `"first-listed"` or
+    /// `"priority"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().replace('_', "-").as_str() {
+            "leftmost" => Some(OverlapPolicy::Leftmost),
+            "longest" => Some(OverlapPolicy::Longest),
+            "first-listed" => Some(OverlapPolicy::FirstListed),
+            "priority" => Some(OverlapPolicy::Priority),
+            _ => None,
+        }
+    }
+
+    /// Returns the name accepted by `from_name`.
+    pub fn name(self) -> &'static str {
+        match self {
+            OverlapPolicy::Leftmost => "leftmost",
+            OverlapPolicy::Longest => "longest",
+            OverlapPolicy::FirstListed => "first-listed",
+            OverlapPolicy::Priority => "priority",
+        }
+    }
+}
+
+/// An occurrence together with what the policies rank it by.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Candidate {
+    pub word_match: WordMatch,
+    /// Position of the word in the word list; context values come last.
+    pub list_index: usize,
+    /// Position of the category in the priority list; lower wins.
+    pub priority: usize,
+    pub is_context_value: bool,
+}
+
+impl Candidate {
+    /// The sort key under `policy`, where the smallest key wins.
+    fn rank(&self, policy: OverlapPolicy) -> (usize, usize, usize) {
+        let start = self.word_match.start;
+        let length = self.word_match.end - start;
+        match policy {
+            OverlapPolicy::Leftmost => (usize::from(!self.is_context_value), start, 0),
+            OverlapPolicy::Longest => (usize::MAX - length, start, 0),
+            OverlapPolicy::FirstListed => (self.list_index, start, 0),
+            OverlapPolicy::Priority => (self.priority, usize::MAX - length, start),
+        }
+    }
+}
+
+/// Selects the occurrences to mask from overlapping candidates.
+///
+/// # Parameters
+///
+/// * `candidates` - Every candidate occurrence, in any order
+/// * `policy` - Decides which of two overlapping candidates wins
+/// * `merge` - Whether overlapping candidates are merged into one span
+///
+/// # Returns
+///
+/// Non-overlapping matches, sorted by start.
+pub(crate) fn resolve_overlaps(
+    mut candidates: Vec<Candidate>,
+    policy: OverlapPolicy,
+    merge: bool,
+) -> Vec<WordMatch> {
+    if merge {
+        return merge_overlaps(candidates, policy);
+    }
+
+    candidates.sort_by_key(|candidate| candidate.rank(policy));
+    let mut accepted: Vec<WordMatch> = Vec::with_capacity(candidates.len());
+
+    for candidate in candidates {
+        let word_match = candidate.word_match;
+        let index = accepted.partition_point(|existing| existing.start < word_match.end);
+        let overlaps_previous = index
+            .checked_sub(1)
+            .is_some_and(|previous| accepted[previous].end > word_match.start);
+
+        if !overlaps_previous {
+            accepted.insert(index, word_match);
+        }
+    }
+
+    accepted
+}
+
+/// Replaces every group of overlapping candidates with a single span that
+/// covers them all and belongs to the group's winning candidate.
+fn merge_overlaps(mut candidates: Vec<Candidate>, policy: OverlapPolicy) -> Vec<WordMatch> {
+    candidates.sort_by_key(|candidate| candidate.word_match.start);
+    let mut merged: Vec<(WordMatch, Candidate)> = Vec::new();
+
+    for candidate in candidates {
+        match merged.last_mut() {
+            Some((span, winner)) if candidate.word_match.start < span.end => {
+                span.end = span.end.max(candidate.word_match.end);
+                if candidate.rank(policy) < winner.rank(policy) {
+                    *winner = candidate;
+                }
+            }
+            _ => merged.push((candidate.word_match, candidate)),
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|(span, winner)| WordMatch {
+            start: span.start,
+            end: span.end,
+            ..winner.word_match
+        })
+        .collect()
+}
@@ -0,0 +1,447 @@
+//! The textual form of field placeholders.
+//!
+//! A template such as `FIELD_{n}` or `[REDACTED_{n}]` is split around its
+//! `{n}` marker. Modifiers naming an alias, a name part or a case variant
+//! follow the number, each introduced by the suffix separator, so the default
+//! format produces `FIELD_1_V2_F` and `[REDACTED_{n}]` with a `:` separator
+//! produces `[REDACTED_1:V2:F]`.
+//!
+//! A template may also contain a `{category}` marker before `{n}`, such as
+//! `{category}_{n}`. Each field is then labeled with its entry's or context
+//! rule's category and numbered within it, producing `PERSON_1`, `EMAIL_1`
+//! and `PERSON_2`. Fields without a category are labeled `FIELD`.
+//!
+//! A session namespace, such as `a7k2`, goes between that text and the
+//! number, as in `FIELD_a7k2_1`, so placeholders from separately masked
+//! documents can be told apart when they end up in one text.
+//!
+//! A format may also write a check character after every field number, as
+//! in `FIELD_12K`. Decoding leaves placeholders whose check character does
+//! not match their number undecoded, which catches most invented field
+//! numbers and every change of one digit or swap of two adjacent digits.
+//!
+//! Text that already looks like a placeholder, such as a literal `FIELD_3`
+//! in pasted masked text or code, is escaped with a backslash after the text
+//! before the number, giving `FIELD_\3`. With categories shown, only text
+//! after a label in use is escaped, so `ISO_9001` stays as it is unless
+//! `ISO` is a category. Decoding removes the backslash instead of decoding
+//! the text, so it comes back untouched.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// The letters check characters are written with. I, O and U are left out
+/// so that the count is prime.
+const CHECK_LETTERS: &[u8] = b"ABCDEFGHJKLMNPQRSTVWXYZ";
+/// The characters of a generated session namespace after its first letter.
+const NAMESPACE_DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+/// The marker a template uses for the field number.
+const NUMBER_MARKER: &str = "{n}";
+/// The marker a template uses for the field's category label.
+const CATEGORY_MARKER: &str = "{category}";
+const DEFAULT_TEMPLATE: &str = "FIELD_{n}";
+const DEFAULT_SEPARATOR: &str = "_";
+/// The label of fields without a category.
+const UNCATEGORIZED_LABEL: &str = "FIELD";
+/// The character that escapes placeholder-like text in the original.
+const ESCAPE: char = '\\';
+
+/// How placeholders are written and recognized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PlaceholderFormat {
+    /// The text before the category label, when categories are shown.
+    lead: Option<String>,
+    prefix: String,
+    /// The session namespace, which always starts with a letter.
+    namespace: Option<String>,
+    /// Whether a check character follows every field number.
+    checked: bool,
+    closing: String,
+    separator: String,
+    /// The category labels in use, after which placeholder-like text is
+    /// escaped when categories are shown.
+    labels: Vec<String>,
+}
+
+impl Default for PlaceholderFormat {
+    fn default() -> Self {
+        PlaceholderFormat {
+            lead: None,
+            prefix: "FIELD_".to_string(),
+            namespace: None,
+            checked: false,
+            closing: String::new(),
+            separator: DEFAULT_SEPARATOR.to_string(),
+            labels: Vec::new(),
+        }
+    }
+}
+
+impl PlaceholderFormat {
+    /// Builds a format from a template containing `{n}` once, with text
+    /// before it, and a non-empty suffix separator.
+    ///
+    /// An optional `{category}` marker must come before `{n}` and be
+    /// separated from it by some text.
+    ///
+    /// Returns `None` when the template or separator is unusable.
+    pub(crate) fn new(template: &str, separator: &str) -> Option<Self> {
+        let (head, closing) = template.split_once(NUMBER_MARKER)?;
+        let (lead, prefix) = match head.split_once(CATEGORY_MARKER) {
+            Some((lead, prefix)) => (Some(lead), prefix),
+            None => (None, head),
+        };
+        if prefix.is_empty()
+            || prefix.ends_with(|c: char| c.is_ascii_digit())
+            || prefix.contains(CATEGORY_MARKER)
+            || closing.contains(NUMBER_MARKER)
+            || closing.contains(CATEGORY_MARKER)
+            || separator.is_empty()
+        {
+            return None;
+        }
+
+        Some(PlaceholderFormat {
+            lead: lead.map(str::to_string),
+            prefix: prefix.to_string(),
+            namespace: None,
+            checked: false,
+            closing: closing.to_string(),
+            separator: separator.to_string(),
+            labels: Vec::new(),
+        })
+    }
+
+    /// Returns this format with another template, falling back to
+    /// `FIELD_{n}` when `template` is `None` or unusable.
+    pub(crate) fn with_template(&self, template: Option<&str>) -> Self {
+        let format = template
+            .and_then(|template| Self::new(template, &self.separator))
+            .or_else(|| Self::new(DEFAULT_TEMPLATE, &self.separator))
+            .unwrap_or_default();
+        self.settings_on(format)
+    }
+
+    /// Returns this format with another suffix separator, falling back to
+    /// `_` when `separator` is `None` or empty.
+    pub(crate) fn with_separator(&self, separator: Option<&str>) -> Self {
+        let template = self.template();
+        let format = separator
+            .and_then(|separator| Self::new(&template, separator))
+            .or_else(|| Self::new(&template, DEFAULT_SEPARATOR))
+            .unwrap_or_default();
+        self.settings_on(format)
+    }
+
+    /// Returns this format with another session namespace, or none when
+    /// `namespace` is `None` or not ASCII letters and digits starting with a
+    /// letter.
+    pub(crate) fn with_namespace(&self, namespace: Option<&str>) -> Self {
+        let namespace = namespace.filter(|namespace| {
+            namespace.starts_with(|c: char| c.is_ascii_alphabetic())
+                && namespace.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+        PlaceholderFormat {
+            namespace: namespace.map(str::to_string),
+            ..self.clone()
+        }
+    }
+
+    /// Returns this format with or without check characters.
+    pub(crate) fn with_check_characters(&self, checked: bool) -> Self {
+        PlaceholderFormat {
+            checked,
+            ..self.clone()
+        }
+    }
+
+    /// Returns this format with the labels of `categories` in use, along
+    /// with the label of fields without a category.
+    pub(crate) fn with_labels<'c>(
+        &self,
+        categories: impl IntoIterator<Item = Option<&'c str>>,
+    ) -> Self {
+        let mut labels = vec![UNCATEGORIZED_LABEL.to_string()];
+        for label in categories.into_iter().map(category_label) {
+            if !labels.contains(&label) {
+                labels.push(label);
+            }
+        }
+        PlaceholderFormat {
+            labels,
+            ..self.clone()
+        }
+    }
+
+    /// Carries the namespace, check characters and labels in use of this
+    /// format over to `format`.
+    fn settings_on(&self, format: Self) -> Self {
+        PlaceholderFormat {
+            namespace: self.namespace.clone(),
+            checked: self.checked,
+            labels: self.labels.clone(),
+            ..format
+        }
+    }
+
+    /// The template this format was built from.
+    pub(crate) fn template(&self) -> String {
+        match &self.lead {
+            Some(lead) => format!(
+                "{}{}{}{}{}",
+                lead, CATEGORY_MARKER, self.prefix, NUMBER_MARKER, self.closing
+            ),
+            None => format!("{}{}{}", self.prefix, NUMBER_MARKER, self.closing),
+        }
+    }
+
+    pub(crate) fn separator(&self) -> &str {
+        &self.separator
+    }
+
+    pub(crate) fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    pub(crate) fn is_checked(&self) -> bool {
+        self.checked
+    }
+
+    /// Whether fields are labeled and numbered by category.
+    pub(crate) fn shows_categories(&self) -> bool {
+        self.lead.is_some()
+    }
+
+    /// The text every placeholder contains, before its namespace and field
+    /// number.
+    pub(crate) fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// The text every placeholder ends with, which may be empty.
+    pub(crate) fn closing(&self) -> &str {
+        &self.closing
+    }
+
+    /// The text before the field number of placeholders with the given
+    /// label, such as `FIELD_`, `[PERSON_` or `FIELD_a7k2_`.
+    pub(crate) fn stem(&self, label: &str) -> String {
+        match &self.lead {
+            Some(lead) => format!("{}{}{}", lead, label, self.number_prefix()),
+            None => self.number_prefix().into_owned(),
+        }
+    }
+
+    /// The text right before every field number, including the namespace.
+    fn number_prefix(&self) -> Cow<'_, str> {
+        match &self.namespace {
+            Some(namespace) => {
+                Cow::Owned(format!("{}{}{}", self.prefix, namespace, self.separator))
+            }
+            None => Cow::Borrowed(&self.prefix),
+        }
+    }
+
+    /// Writes the placeholder for a labeled field number followed by modifier
+    /// codes such as `"V2"` or `"F"`.
+    pub(crate) fn render(&self, label: &str, field_num: usize, modifiers: &[&str]) -> String {
+        let mut placeholder = format!("{}{}", self.stem(label), field_num);
+        if self.checked {
+            placeholder.push(check_character(field_num));
+        }
+        for modifier in modifiers.iter().filter(|modifier| !modifier.is_empty()) {
+            placeholder.push_str(&self.separator);
+            placeholder.push_str(modifier);
+        }
+        placeholder.push_str(&self.closing);
+        placeholder
+    }
+
+    /// Escapes every run of the original text that could be read as a
+    /// placeholder, or as an escaped one.
+    pub(crate) fn escape<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        let mut escaped = String::new();
+        self.push_escaped(text, 0..text.len(), &mut escaped);
+        if escaped.len() == text.len() {
+            return Cow::Borrowed(text);
+        }
+        Cow::Owned(escaped)
+    }
+
+    /// Appends the `span` of the original text to `output`, escaping the
+    /// runs in it that could be read as a placeholder. The text around the
+    /// span is only looked at, so a label before it is still seen.
+    pub(crate) fn push_escaped(&self, text: &str, span: Range<usize>, output: &mut String) {
+        let mut cursor = span.start;
+        for (offset, _) in text[span.clone()].char_indices() {
+            let start = span.start + offset;
+            if start < cursor {
+                continue;
+            }
+            if let Some(number_start) = self
+                .collision_at(text, start)
+                .filter(|number_start| *number_start < span.end)
+            {
+                output.push_str(&text[cursor..number_start]);
+                output.push(ESCAPE);
+                cursor = number_start;
+            }
+        }
+        output.push_str(&text[cursor..span.end]);
+    }
+
+    /// Returns the span of the escape if an escaped run starts at `start`.
+    /// Dropping that span restores the original text.
+    pub(crate) fn escape_at(&self, text: &str, start: usize) -> Option<Range<usize>> {
+        self.collision_at(text, start)
+            .filter(|number_start| text[*number_start..].starts_with(ESCAPE))
+            .map(|number_start| number_start..number_start + ESCAPE.len_utf8())
+    }
+
+    /// Returns the position after the text before the number if it starts at
+    /// `start` and is followed by any number of escapes and a digit, after a
+    /// label in use when the format shows categories.
+    fn collision_at(&self, text: &str, start: usize) -> Option<usize> {
+        let number_prefix = self.number_prefix();
+        if !text[start..].starts_with(number_prefix.as_ref()) {
+            return None;
+        }
+        let number_start = start + number_prefix.len();
+        if !text[number_start..]
+            .trim_start_matches(ESCAPE)
+            .starts_with(|c: char| c.is_ascii_digit())
+        {
+            return None;
+        }
+
+        let Some(lead) = &self.lead else {
+            return Some(number_start);
+        };
+        self.labels
+            .iter()
+            .any(|label| {
+                text[..start]
+                    .strip_suffix(label.as_str())
+                    .is_some_and(|before| before.ends_with(lead.as_str()))
+            })
+            .then_some(number_start)
+    }
+
+    /// Returns the position after the modifier `code` if it starts at
+    /// `start`.
+    pub(crate) fn modifier_end(&self, text: &str, start: usize, code: &str) -> Option<usize> {
+        let rest = text[start..].strip_prefix(self.separator.as_str())?;
+        rest.starts_with(code)
+            .then_some(start + self.separator.len() + code.len())
+    }
+}
+
+/// Returns the check character for a field number: the sum of its digits,
+/// each weighted by its position from the right, modulo 23.
+pub(crate) fn check_character(field_num: usize) -> char {
+    let sum: usize = field_num
+        .to_string()
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(position, digit)| (position + 1) * usize::from(digit - b'0'))
+        .sum();
+    char::from(CHECK_LETTERS[sum % CHECK_LETTERS.len()])
+}
+
+/// Generates a random session namespace of a lowercase letter followed by
+/// three lowercase letters or digits, such as `a7k2`.
+pub(crate) fn random_namespace() -> String {
+    let pick = |choices: &[u8]| {
+        let index = (js_sys::Math::random() * choices.len() as f64) as usize;
+        char::from(choices[index.min(choices.len() - 1)])
+    };
+    let mut namespace = String::new();
+    namespace.push(pick(&NAMESPACE_DIGITS[10..]));
+    for _ in 0..3 {
+        namespace.push(pick(NAMESPACE_DIGITS));
+    }
+    namespace
+}
+
+/// The label and number every field is written with.
+///
+/// Fields are numbered from one in the order they are added, either all
+/// together or, when the format shows categories, separately within each
+/// category label.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FieldNumbers {
+    by_category: bool,
+    /// Every label with the indexes of its fields in numbering order.
+    groups: Vec<(String, Vec<usize>)>,
+    /// The group and one-based number of every field.
+    fields: Vec<(usize, usize)>,
+}
+
+impl FieldNumbers {
+    pub(crate) fn new(format: &PlaceholderFormat) -> Self {
+        FieldNumbers {
+            by_category: format.shows_categories(),
+            ..FieldNumbers::default()
+        }
+    }
+
+    /// Numbers the next field.
+    pub(crate) fn push(&mut self, category: Option<&str>) {
+        let label = if self.by_category {
+            category_label(category)
+        } else {
+            String::new()
+        };
+        let group = match self.groups.iter().position(|(listed, _)| *listed == label) {
+            Some(group) => group,
+            None => {
+                self.groups.push((label, Vec::new()));
+                self.groups.len() - 1
+            }
+        };
+
+        let indexes = &mut self.groups[group].1;
+        indexes.push(self.fields.len());
+        self.fields.push((group, indexes.len()));
+    }
+
+    /// The label and number of the field at `index`.
+    pub(crate) fn get(&self, index: usize) -> (&str, usize) {
+        let (group, number) = self.fields[index];
+        (&self.groups[group].0, number)
+    }
+
+    /// Every label with the indexes of its fields in numbering order.
+    pub(crate) fn groups(&self) -> &[(String, Vec<usize>)] {
+        &self.groups
+    }
+}
+
+/// Converts a category into the label placeholders show, such as `"PERSON"`
+/// for `"person"` or `"ORG_NAME"` for `"org name"`.
+fn category_label(category: Option<&str>) -> String {
+    let label: String = category
+        .unwrap_or_default()
+        .chars()
+        .flat_map(|c| {
+            if c.is_alphanumeric() {
+                c.to_uppercase().collect()
+            } else {
+                vec!['_']
+            }
+        })
+        .collect();
+    let label = label.trim_matches('_');
+
+    if label.is_empty() {
+        UNCATEGORIZED_LABEL.to_string()
+    } else {
+        label.to_string()
+    }
+}
+
+/// Converts a case suffix such as `"_F"` into its modifier code `"F"`.
+pub(crate) fn case_code(case_suffix: &str) -> &str {
+    case_suffix.strip_prefix('_').unwrap_or(case_suffix)
+}
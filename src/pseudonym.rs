@@ -0,0 +1,554 @@
+//! Realistic fake values for pseudonym masking.
+//!
+//! Every masked value is replaced with a fake value of the same kind drawn
+//! from the bundled lists below: a person's name keeps its number of words,
+//! its initials and, when the first name is known, its gender; an email
+//! keeps the layout of its local part; a company keeps its legal suffix.
+//! Values of no recognized kind keep their shape, with letters and digits
+//! replaced by others of the same case.
+//!
+//! Choices are derived from an HMAC-SHA256 of the original value and a seed
+//! under a key, so a value maps to the same pseudonym whenever the key and
+//! seed are the same, and nobody without the key can reproduce the choices.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+const FEMALE_FIRST_NAMES: &[&str] = &[
+    "Alice", "Amelia", "Anna", "Beatrice", "Carla", "Chloe", "Clara", "Daisy", "Diana", "Edith",
+    "Elena", "Emma", "Fiona", "Grace", "Hannah", "Helen", "Irene", "Isla", "Julia", "Karen",
+    "Laura", "Lily", "Lucy", "Maria", "Megan", "Nina", "Olivia", "Paula", "Rachel", "Rose",
+    "Sarah", "Sophie", "Tessa", "Vera", "Wendy", "Zoe",
+];
+
+const MALE_FIRST_NAMES: &[&str] = &[
+    "Adam", "Albert", "Arthur", "Ben", "Carl", "Daniel", "David", "Edward", "Felix", "Frank",
+    "George", "Henry", "Hugo", "Isaac", "Jack", "James", "Kevin", "Leo", "Lucas", "Mark", "Martin",
+    "Nathan", "Oscar", "Owen", "Patrick", "Paul", "Peter", "Robert", "Samuel", "Simon", "Thomas",
+    "Victor", "Walter", "William",
+];
+
+/// Common first names that are not in the lists above, used to recognize
+/// the gender of original names.
+const OTHER_FEMALE_NAMES: &[&str] = &[
+    "Ana",
+    "Catherine",
+    "Elizabeth",
+    "Emily",
+    "Jane",
+    "Jennifer",
+    "Jessica",
+    "Linda",
+    "Mary",
+    "Patricia",
+    "Susan",
+];
+
+const OTHER_MALE_NAMES: &[&str] = &[
+    "Bob",
+    "Charles",
+    "Christopher",
+    "Joe",
+    "John",
+    "Joseph",
+    "Matthew",
+    "Michael",
+    "Richard",
+    "Steven",
+    "Tom",
+];
+
+const SURNAMES: &[&str] = &[
+    "Abbott", "Bailey", "Barker", "Bennett", "Carter", "Clarke", "Cooper", "Dawson", "Ellis",
+    "Fisher", "Fletcher", "Foster", "Gibson", "Graham", "Hayes", "Holland", "Hughes", "Jenkins",
+    "Kendall", "Lawson", "Marsh", "Mills", "Morgan", "Norris", "Palmer", "Parker", "Porter",
+    "Quinn", "Reed", "Russell", "Saunders", "Shaw", "Spencer", "Sutton", "Turner", "Walsh",
+    "Warren", "Webb", "Wells", "Wheeler",
+];
+
+const COMPANIES: &[&str] = &[
+    "Bluefield",
+    "Brightway",
+    "Cedarline",
+    "Clearpath",
+    "Copperleaf",
+    "Eastgate",
+    "Fairhaven",
+    "Greystone",
+    "Harborview",
+    "Ironbridge",
+    "Lakeshore",
+    "Maplewood",
+    "Northwind",
+    "Oakridge",
+    "Pinecrest",
+    "Redwood",
+    "Silverline",
+    "Stonebrook",
+    "Summit Peak",
+    "Westbrook",
+];
+
+/// Legal suffixes that mark a company name and are kept as written.
+const COMPANY_SUFFIXES: &[&str] = &[
+    "ag",
+    "co",
+    "co.",
+    "corp",
+    "corp.",
+    "corporation",
+    "gmbh",
+    "inc",
+    "inc.",
+    "llc",
+    "llp",
+    "ltd",
+    "ltd.",
+    "plc",
+    "s.a.",
+    "sa",
+];
+
+const CITIES: &[&str] = &[
+    "Ashford",
+    "Brookfield",
+    "Clearwater",
+    "Dunmore",
+    "Elmwood",
+    "Fairview",
+    "Glenford",
+    "Greenville",
+    "Hillsborough",
+    "Kingsbridge",
+    "Lakewood",
+    "Millbrook",
+    "Newbury",
+    "Oakdale",
+    "Pinehurst",
+    "Riverton",
+    "Rosedale",
+    "Springfield",
+    "Westfield",
+    "Woodbury",
+];
+
+/// Domains reserved for documentation, so fake addresses never reach anyone.
+const EMAIL_DOMAINS: &[&str] = &["example.com", "example.net", "example.org"];
+
+/// What kind of value a pseudonym stands in for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PseudonymKind {
+    Person,
+    Company,
+    Email,
+    City,
+    /// Anything else, which keeps its shape.
+    Other,
+}
+
+impl PseudonymKind {
+    /// Determines the kind of `value` from its category, or from its shape
+    /// when it has none.
+    ///
+    /// Categories mentioning names or people, companies or organizations,
+    /// email or mail, and cities or towns are recognized. Uncategorized values
+    /// containing `@` are emails, those ending in a legal suffix such as
+    /// `Inc.` are companies, and other purely alphabetic ones are names.
+    pub(crate) fn of(value: &str, category: Option<&str>) -> Self {
+        if let Some(category) = category {
+            let category = category.to_lowercase();
+            let mentions = |words: &[&str]| words.iter().any(|word| category.contains(word));
+            return if mentions(&["mail"]) {
+                PseudonymKind::Email
+            } else if mentions(&["name", "person", "people"]) {
+                PseudonymKind::Person
+            } else if mentions(&["company", "org", "business", "employer"]) {
+                PseudonymKind::Company
+            } else if mentions(&["city", "town"]) {
+                PseudonymKind::City
+            } else {
+                PseudonymKind::Other
+            };
+        }
+
+        if value.contains('@') {
+            PseudonymKind::Email
+        } else if company_suffix(value).is_some() {
+            PseudonymKind::Company
+        } else if value
+            .chars()
+            .all(|c| c.is_alphabetic() || c.is_whitespace() || matches!(c, '.' | '-' | '\''))
+        {
+            PseudonymKind::Person
+        } else {
+            PseudonymKind::Other
+        }
+    }
+}
+
+/// Generates the pseudonym for `value`.
+///
+/// # Parameters
+///
+/// * `value` - The original value, as listed
+/// * `kind` - The kind of value
+/// * `key` - Varies the pseudonyms chosen for the same values
+/// * `seed` - Varies the pseudonyms chosen under the same key
+/// * `attempt` - Incremented to draw another pseudonym after a collision
+///
+/// # Returns
+///
+/// A fake value of the same kind, in title case for names, companies and
+/// cities.
+pub(crate) fn pseudonym(
+    value: &str,
+    kind: PseudonymKind,
+    key: &str,
+    seed: u32,
+    attempt: u32,
+) -> String {
+    let mut draw = Draw::new(&value.to_lowercase(), key, seed, attempt);
+
+    match kind {
+        PseudonymKind::Person => fake_name(value, &mut draw),
+        PseudonymKind::Company => {
+            let name = draw.pick(COMPANIES);
+            match company_suffix(value) {
+                Some(suffix) => format!("{} {}", name, suffix),
+                None => name.to_string(),
+            }
+        }
+        PseudonymKind::Email => fake_email(value, &mut draw),
+        PseudonymKind::City => draw.pick(CITIES).to_string(),
+        PseudonymKind::Other => fake_shape(value, &mut draw),
+    }
+}
+
+/// A sequence of deterministic choices seeded by a value.
+struct Draw {
+    /// An HMAC-SHA256 under the key, fed with the seed, the attempt and the
+    /// value.
+    mac: Hmac<Sha256>,
+    counter: u64,
+}
+
+impl Draw {
+    fn new(value: &str, key: &str, seed: u32, attempt: u32) -> Self {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any length");
+        mac.update(&seed.to_le_bytes());
+        mac.update(&attempt.to_le_bytes());
+        mac.update(value.as_bytes());
+        Draw { mac, counter: 0 }
+    }
+
+    /// Returns the next number below `bound`, taken from the HMAC of the
+    /// number of draws so far.
+    fn below(&mut self, bound: usize) -> usize {
+        let mut mac = self.mac.clone();
+        mac.update(&self.counter.to_le_bytes());
+        self.counter += 1;
+
+        let digest = mac.finalize().into_bytes();
+        let mut number = [0; 8];
+        number.copy_from_slice(&digest[..8]);
+        (u64::from_le_bytes(number) % bound as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, list: &[&'a str]) -> &'a str {
+        list[self.below(list.len())]
+    }
+}
+
+/// Returns the legal suffix ending a name of several words, if any.
+fn company_suffix(value: &str) -> Option<&str> {
+    let mut words = value.split_whitespace();
+    let last = words.next_back()?;
+    words.next()?;
+    COMPANY_SUFFIXES
+        .contains(&last.to_lowercase().as_str())
+        .then_some(last)
+}
+
+/// Whether `word` is an initial such as `J.` or `J`.
+fn is_initial(word: &str) -> bool {
+    word.trim_end_matches('.').chars().count() == 1
+}
+
+/// Returns the list of first names of the same gender as `word`, if it is a
+/// known first name.
+fn first_names_like(word: &str) -> Option<&'static [&'static str]> {
+    let listed_in = |lists: &[&[&str]]| {
+        lists
+            .iter()
+            .any(|list| list.iter().any(|name| name.eq_ignore_ascii_case(word)))
+    };
+
+    if listed_in(&[FEMALE_FIRST_NAMES, OTHER_FEMALE_NAMES]) {
+        Some(FEMALE_FIRST_NAMES)
+    } else if listed_in(&[MALE_FIRST_NAMES, OTHER_MALE_NAMES]) {
+        Some(MALE_FIRST_NAMES)
+    } else {
+        None
+    }
+}
+
+/// Picks a list of first names of either gender.
+fn any_first_names(draw: &mut Draw) -> &'static [&'static str] {
+    if draw.below(2) == 0 {
+        FEMALE_FIRST_NAMES
+    } else {
+        MALE_FIRST_NAMES
+    }
+}
+
+/// Replaces every word of a name, keeping initials as initials, hyphenated
+/// words hyphenated and the whitespace between words.
+///
+/// The last word becomes a surname, unless the name is a single known first
+/// name.
+fn fake_name(value: &str, draw: &mut Draw) -> String {
+    let word_count = value.split_whitespace().count();
+    let given = value.split_whitespace().find(|word| !is_initial(word));
+    let known_given = given.and_then(first_names_like);
+    let first_names = known_given.unwrap_or_else(|| any_first_names(draw));
+    let surname_index = if word_count == 1 && known_given.is_some() {
+        None
+    } else {
+        word_count.checked_sub(1)
+    };
+
+    let mut output = String::with_capacity(value.len());
+    let mut rest = value;
+    let mut word_index = 0;
+    while !rest.is_empty() {
+        let space_len = rest.len() - rest.trim_start().len();
+        output.push_str(&rest[..space_len]);
+        rest = &rest[space_len..];
+
+        let word_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let list = if Some(word_index) == surname_index {
+            SURNAMES
+        } else {
+            first_names
+        };
+        let fake: Vec<String> = rest[..word_len]
+            .split('-')
+            .map(|part| fake_word(part, list, draw))
+            .collect();
+        output.push_str(&fake.join("-"));
+        rest = &rest[word_len..];
+        word_index += 1;
+    }
+
+    output
+}
+
+/// Replaces a single word with a name from `list`, or an initial with the
+/// initial of one.
+fn fake_word(word: &str, list: &[&str], draw: &mut Draw) -> String {
+    if word.is_empty() {
+        return String::new();
+    }
+
+    let name = draw.pick(list);
+    if is_initial(word) {
+        let mut rest = word.chars();
+        rest.next();
+        name.chars().take(1).chain(rest).collect()
+    } else {
+        name.to_string()
+    }
+}
+
+fn is_local_separator(c: char) -> bool {
+    matches!(c, '.' | '_' | '-' | '+')
+}
+
+/// Replaces an email address with one at a documentation domain whose
+/// local part has the same separators, with names in place of words and
+/// digits in place of digits.
+fn fake_email(value: &str, draw: &mut Draw) -> String {
+    let local = value.rsplit_once('@').map_or(value, |(local, _)| local);
+    let mut pieces: Vec<(&str, Option<char>)> = Vec::new();
+    let mut start = 0;
+    for (index, c) in local.char_indices() {
+        if is_local_separator(c) {
+            pieces.push((&local[start..index], Some(c)));
+            start = index + c.len_utf8();
+        }
+    }
+    pieces.push((&local[start..], None));
+
+    let first_names = any_first_names(draw);
+    let mut fake_local = String::with_capacity(local.len());
+    for (index, (piece, separator)) in pieces.iter().enumerate() {
+        let digits = piece.len() - piece.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+        let letters = &piece[..piece.len() - digits];
+
+        if is_initial(letters) {
+            fake_local.push(char::from(b'a' + draw.below(26) as u8));
+        } else if !letters.is_empty() {
+            let is_surname = index + 1 == pieces.len() && pieces.len() > 1;
+            let list = if is_surname { SURNAMES } else { first_names };
+            fake_local.push_str(&draw.pick(list).to_lowercase());
+        }
+        fake_local.push_str(&fake_shape(&piece[piece.len() - digits..], draw));
+        fake_local.extend(*separator);
+    }
+
+    format!("{}@{}", fake_local, draw.pick(EMAIL_DOMAINS))
+}
+
+/// Replaces every letter with a letter of the same case, or of the same
+/// script when it has no case, and every digit with a digit of the same
+/// script, keeping everything else.
+///
+/// A number that does not start with zero keeps a nonzero first digit, and
+/// one of twelve or more digits that passes the Luhn check, such as a card
+/// number, gets a check digit that passes it too. Other numeric characters,
+/// such as `²`, become ASCII digits.
+fn fake_shape(value: &str, draw: &mut Draw) -> String {
+    let mut previous_is_digit = false;
+    let mut fake: Vec<char> = value
+        .chars()
+        .map(|c| {
+            let starts_number = !previous_is_digit;
+            previous_is_digit = c.is_numeric();
+            if let Some((zero, digit)) = decimal_digit(c) {
+                let fake_digit = if starts_number && digit != 0 {
+                    1 + draw.below(9)
+                } else {
+                    draw.below(10)
+                };
+                script_digit(zero, fake_digit as u32)
+            } else if c.is_numeric() && !c.is_alphabetic() {
+                char::from(b'0' + draw.below(10) as u8)
+            } else if c.is_uppercase() {
+                char::from(b'A' + draw.below(26) as u8)
+            } else if c.is_lowercase() {
+                char::from(b'a' + draw.below(26) as u8)
+            } else if c.is_alphabetic() {
+                caseless_letter(c, draw)
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    let original_digits: Vec<u32> = value
+        .chars()
+        .filter_map(|c| decimal_digit(c).map(|(_, digit)| digit))
+        .collect();
+    if original_digits.len() >= 12 && luhn_sum(&original_digits).is_multiple_of(10) {
+        let fake_digits: Vec<u32> = fake
+            .iter()
+            .filter_map(|&c| decimal_digit(c).map(|(_, digit)| digit))
+            .collect();
+        let check_digit = luhn_check_digit(&fake_digits[..fake_digits.len() - 1]);
+        if let Some(last) = fake.iter_mut().rev().find(|c| decimal_digit(**c).is_some()) {
+            if let Some((zero, _)) = decimal_digit(*last) {
+                *last = script_digit(zero, check_digit);
+            }
+        }
+    }
+
+    fake.into_iter().collect()
+}
+
+/// The zeros of the scripts whose ten decimal digits are consecutive, from
+/// which a digit is drawn in the same script.
+const DIGIT_ZEROS: &[char] = &[
+    '0',        // ASCII
+    '\u{0660}', // Arabic-Indic
+    '\u{06F0}', // Extended Arabic-Indic
+    '\u{07C0}', // NKo
+    '\u{0966}', // Devanagari
+    '\u{09E6}', // Bengali
+    '\u{0A66}', // Gurmukhi
+    '\u{0AE6}', // Gujarati
+    '\u{0B66}', // Oriya
+    '\u{0BE6}', // Tamil
+    '\u{0C66}', // Telugu
+    '\u{0CE6}', // Kannada
+    '\u{0D66}', // Malayalam
+    '\u{0DE6}', // Sinhala
+    '\u{0E50}', // Thai
+    '\u{0ED0}', // Lao
+    '\u{0F20}', // Tibetan
+    '\u{1040}', // Myanmar
+    '\u{17E0}', // Khmer
+    '\u{1810}', // Mongolian
+    '\u{FF10}', // Fullwidth
+];
+
+/// The zero of the script of a decimal digit and the digit's value.
+fn decimal_digit(c: char) -> Option<(char, u32)> {
+    DIGIT_ZEROS.iter().find_map(|&zero| {
+        let value = (c as u32).checked_sub(zero as u32)?;
+        if value < 10 {
+            Some((zero, value))
+        } else {
+            None
+        }
+    })
+}
+
+/// The digit of a value in the script that starts at `zero`.
+fn script_digit(zero: char, value: u32) -> char {
+    std::char::from_u32(zero as u32 + value).unwrap_or(zero)
+}
+
+/// Ranges of letters without case, from which a letter of the same script
+/// is drawn.
+const CASELESS_LETTERS: &[(char, char)] = &[
+    ('\u{05D0}', '\u{05EA}'), // Hebrew
+    ('\u{0621}', '\u{063A}'), // Arabic
+    ('\u{0641}', '\u{064A}'),
+    ('\u{0915}', '\u{0939}'), // Devanagari
+    ('\u{0E01}', '\u{0E2E}'), // Thai
+    ('\u{3041}', '\u{3096}'), // Hiragana
+    ('\u{30A1}', '\u{30FA}'), // Katakana
+    ('\u{4E00}', '\u{9FFF}'), // CJK ideographs
+    ('\u{AC00}', '\u{D7A3}'), // Hangul syllables
+];
+
+/// Replaces a letter without case with another of its script, or with a
+/// lowercase ASCII letter when its script is not listed, so the original
+/// letter never survives.
+fn caseless_letter(c: char, draw: &mut Draw) -> char {
+    let Some(&(first, last)) = CASELESS_LETTERS
+        .iter()
+        .find(|(first, last)| (*first..=*last).contains(&c))
+    else {
+        return char::from(b'a' + draw.below(26) as u8);
+    };
+
+    let span = last as u32 - first as u32 + 1;
+    let offset = draw.below(span as usize - 1) as u32;
+    let code = first as u32 + offset;
+    // Skip the original letter, so every draw changes it.
+    let code = if code >= c as u32 { code + 1 } else { code };
+    char::from_u32(code).unwrap_or(c)
+}
+
+/// The Luhn sum of `digits`, which ends with the check digit.
+fn luhn_sum(digits: &[u32]) -> u32 {
+    digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, digit)| match (index % 2, digit * 2) {
+            (0, _) => *digit,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum()
+}
+
+/// The check digit that makes `payload` followed by it pass the Luhn check.
+fn luhn_check_digit(payload: &[u32]) -> u32 {
+    let mut with_zero = payload.to_vec();
+    with_zero.push(0);
+    (10 - luhn_sum(&with_zero) % 10) % 10
+}
@@ -0,0 +1,174 @@
+//! Partial masking that leaves some characters of a value visible.
+//!
+//! An entry, or every entry and context rule of a category, can keep its
+//! first and last characters, the domain of an email address or the country
+//! code of an international phone number, as in `j***@example.com` or
+//! `************1111`. The rest is masked in the configured mask style.
+//! Characters are counted as user-perceived characters.
+
+use unicode_segmentation::UnicodeSegmentation;
+use wasm_bindgen::prelude::*;
+
+use crate::mask_style::MaskStyle;
+
+/// Which characters of a value stay visible when it is masked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Reveal {
+    pub keep_first: usize,
+    pub keep_last: usize,
+    /// Keeps everything from the last `@` on.
+    pub keep_domain: bool,
+    /// Keeps the `+` and country code that start a phone number.
+    pub keep_country_code: bool,
+}
+
+impl Reveal {
+    /// Masks `matched` in `style` except for the characters to keep, hiding
+    /// each run of other characters as a whole.
+    ///
+    /// A value that would be kept whole is masked whole instead.
+    ///
+    /// # Returns
+    ///
+    /// The masked value and the runs of characters left visible, in order.
+    pub(crate) fn apply(&self, matched: &str, style: &MaskStyle) -> (String, Vec<String>) {
+        let graphemes: Vec<&str> = matched.graphemes(true).collect();
+        let count = graphemes.len();
+        let domain_start = self
+            .keep_domain
+            .then(|| graphemes.iter().rposition(|grapheme| *grapheme == "@"))
+            .flatten()
+            .unwrap_or(count);
+        let country_code_end = if self.keep_country_code {
+            country_code_len(matched)
+        } else {
+            0
+        };
+
+        let kept: Vec<bool> = (0..count)
+            .map(|index| {
+                index < self.keep_first.max(country_code_end)
+                    || index + self.keep_last >= count
+                    || index >= domain_start
+            })
+            .collect();
+        if kept.iter().all(|kept| *kept) {
+            return (style.hide(matched), Vec::new());
+        }
+
+        let mut masked = String::with_capacity(matched.len());
+        let mut visible: Vec<String> = Vec::new();
+        let mut run = String::new();
+        for (index, grapheme) in graphemes.iter().enumerate() {
+            run.push_str(grapheme);
+            let run_ends = graphemes.get(index + 1).is_none() || kept[index + 1] != kept[index];
+            if !run_ends {
+                continue;
+            }
+
+            if kept[index] {
+                masked.push_str(&run);
+                visible.push(std::mem::take(&mut run));
+            } else {
+                masked.push_str(&style.hide(&run));
+                run.clear();
+            }
+        }
+
+        (masked, visible)
+    }
+}
+
+/// Returns the number of characters of the `+` and country code that start
+/// an international phone number, or zero.
+fn country_code_len(value: &str) -> usize {
+    let Some(digits) = value.strip_prefix('+') else {
+        return 0;
+    };
+    let leading: Vec<u32> = digits
+        .chars()
+        .take(3)
+        .map_while(|c| c.to_digit(10))
+        .collect();
+
+    let code_len = match leading.as_slice() {
+        [1, ..] | [7, ..] => 1,
+        [2, 0, ..] | [2, 7, ..] => 2,
+        [3, 0..=4, ..] | [3, 6, ..] | [3, 9, ..] => 2,
+        [4, 0 | 1 | 3..=9, ..] => 2,
+        [5, 1..=8, ..] => 2,
+        [6, 0..=6, ..] => 2,
+        [8, 1 | 2 | 4 | 6, ..] => 2,
+        [9, 0..=5, ..] | [9, 8, ..] => 2,
+        _ => 3,
+    };
+
+    if leading.len() < code_len {
+        0
+    } else {
+        code_len + 1
+    }
+}
+
+/// A masked occurrence that left some of its characters visible.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct RevealedOccurrence {
+    masked: String,
+    visible: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl RevealedOccurrence {
+    /// The occurrence as written in the masked text.
+    #[wasm_bindgen(getter)]
+    pub fn masked(&self) -> String {
+        self.masked.clone()
+    }
+
+    /// The runs of original characters left visible, in order.
+    #[wasm_bindgen(getter)]
+    pub fn visible(&self) -> Vec<String> {
+        self.visible.clone()
+    }
+}
+
+/// The output of an asterisk masking pass together with every occurrence
+/// that was only partially masked.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct AsteriskResult {
+    text: String,
+    revealed: Vec<RevealedOccurrence>,
+}
+
+#[wasm_bindgen]
+impl AsteriskResult {
+    /// The masked text.
+    #[wasm_bindgen(getter)]
+    pub fn text(&self) -> String {
+        self.text.clone()
+    }
+
+    /// The partially masked occurrences, in text order.
+    #[wasm_bindgen(getter)]
+    pub fn revealed(&self) -> Vec<RevealedOccurrence> {
+        self.revealed.clone()
+    }
+}
+
+impl AsteriskResult {
+    pub(crate) fn new(text: String, revealed: Vec<(String, Vec<String>)>) -> Self {
+        AsteriskResult {
+            text,
+            revealed: revealed
+                .into_iter()
+                .map(|(masked, visible)| RevealedOccurrence { masked, visible })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn into_text(self) -> String {
+        self.text
+    }
+}
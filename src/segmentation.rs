@@ -0,0 +1,67 @@
+//! Token boundaries for scripts that are written without spaces.
+//!
+//! Chinese, Japanese, Thai and the other scripts of mainland Southeast Asia do
+//! not separate words with spaces, so a mask word can occur inside or across
+//! the words of a sentence. With the `segmentation` feature, text in these
+//! scripts is split with the bundled ICU dictionaries, and an occurrence only
+//! counts as a match when it starts and ends on a token boundary.
+
+/// The token boundaries of a text, as sorted byte offsets.
+pub(crate) struct TokenBoundaries {
+    boundaries: Vec<usize>,
+}
+
+impl TokenBoundaries {
+    /// Segments `text`, or returns `None` when it contains no characters from
+    /// a script written without spaces.
+    #[cfg(feature = "segmentation")]
+    pub(crate) fn find(text: &str) -> Option<Self> {
+        if !text.chars().any(is_unspaced_script) {
+            return None;
+        }
+
+        let segmenter = icu_segmenter::WordSegmenter::new_dictionary();
+        Some(TokenBoundaries {
+            boundaries: segmenter.segment_str(text).collect(),
+        })
+    }
+
+    /// Segmentation is unavailable without the `segmentation` feature.
+    #[cfg(not(feature = "segmentation"))]
+    pub(crate) fn find(_text: &str) -> Option<Self> {
+        None
+    }
+
+    /// Whether the span `start..end` begins or ends inside a token of a
+    /// script written without spaces.
+    ///
+    /// Edges next to spaces, punctuation or letters of other scripts are left
+    /// to the usual substring matching.
+    pub(crate) fn splits_token(&self, text: &str, start: usize, end: usize) -> bool {
+        [start, end].iter().any(|&index| {
+            let before = text[..index].chars().next_back();
+            let after = text[index..].chars().next();
+            before.is_some_and(is_unspaced_script)
+                && after.is_some_and(is_unspaced_script)
+                && self.boundaries.binary_search(&index).is_err()
+        })
+    }
+}
+
+/// Whether a character belongs to a script that is written without spaces
+/// between words.
+fn is_unspaced_script(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0E00}'..='\u{0EFF}'     // Thai, Lao
+            | '\u{1000}'..='\u{109F}' // Myanmar
+            | '\u{1780}'..='\u{17FF}' // Khmer
+            | '\u{3040}'..='\u{30FF}' // Hiragana, Katakana
+            | '\u{31F0}'..='\u{31FF}' // Katakana phonetic extensions
+            | '\u{3400}'..='\u{4DBF}' // CJK extension A
+            | '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+            | '\u{F900}'..='\u{FAFF}' // CJK compatibility ideographs
+            | '\u{FF66}'..='\u{FF9F}' // Halfwidth Katakana
+            | '\u{20000}'..='\u{3134F}' // CJK extensions B to G
+    )
+}
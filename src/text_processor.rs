@@ -3,56 +3,73 @@
 ///
 /// This module contains the core functionality for masking sensitive words
 /// in text with various replacement strategies and decoding masked text.
-use js_sys::{Array, Set};
-use regex::{Captures, Regex, RegexBuilder};
+use js_sys::Set;
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use wasm_bindgen::JsValue;
 use web_sys::console;
 
-use crate::case_utils::{capitalize_first, determine_case_suffix};
+use crate::case_utils::{
+    apply_case_mask, capitalize_first_in, case_mask_width, determine_case_suffix,
+    mixed_case_suffix, to_title_case, Locale,
+};
+use crate::mapping::{DecodeResult, FieldMapping, LenientMatch, MaskResult, PseudonymMapping};
+use crate::matcher::{WordMatch, WordMatcher};
+use crate::options::MaskOptions;
+use crate::placeholder::{
+    case_code, check_character, random_namespace, FieldNumbers, PlaceholderFormat,
+};
+use crate::pseudonym::{pseudonym, PseudonymKind};
+use crate::reveal::AsteriskResult;
+use crate::token::{vault_token_end, vault_variant_end, TokenKey, TokenVault, TOKEN_PREFIX};
+use crate::word_list::{entries_from_set, set_to_sorted_vec, MaskEntry, NamePart};
 
 #[derive(Debug, Clone)]
 struct FieldVariants {
     lowercase: String,
     first_upper: String,
+    title_case: String,
     uppercase: String,
 }
 
-/// Converts a JavaScript Set to a sorted Vec of strings.
-///
-/// # Parameters
-///
-/// * `mask_words` - The JavaScript Set containing words to process
-///
-/// # Returns
-///
-/// A vector of (String, usize) tuples, sorted by word length in descending order
-fn set_to_sorted_vec(mask_words: &Set) -> Vec<(String, usize)> {
-    let words: Array = Array::from(mask_words);
-    let words_len = words.length();
-
-    if words_len == 0 {
-        return Vec::new();
+impl FieldVariants {
+    fn new(word: &str, locale: Locale) -> Self {
+        let lowercase = locale.to_lowercase(word);
+        FieldVariants {
+            uppercase: locale.to_uppercase(word),
+            first_upper: capitalize_first_in(&lowercase, locale),
+            title_case: to_title_case(&lowercase, locale),
+            lowercase,
+        }
     }
 
-    let mut word_vec: Vec<(String, usize)> = Vec::with_capacity(words_len as usize);
-    let mut seen_lowercase: HashSet<String> = HashSet::new();
+    /// Variants for a case-sensitive word, which decodes exactly as listed
+    /// whatever the suffix.
+    fn exact(word: &str) -> Self {
+        FieldVariants {
+            lowercase: word.to_string(),
+            first_upper: word.to_string(),
+            title_case: word.to_string(),
+            uppercase: word.to_string(),
+        }
+    }
 
-    for i in 0..words_len {
-        if let Some(word) = words.get(i).as_string() {
-            if word.is_empty() {
-                continue;
-            }
+    /// Whether the word has more than one word, so that it can be written
+    /// in title case.
+    fn is_phrase(&self) -> bool {
+        self.lowercase.split_whitespace().nth(1).is_some()
+    }
 
-            let lowercase = word.to_lowercase();
-            if seen_lowercase.insert(lowercase) {
-                word_vec.push((word, i as usize));
-            }
+    /// Returns the variant a stateless decoder produces for the given suffix.
+    fn for_suffix(&self, case_suffix: &str) -> &str {
+        match case_suffix {
+            "_A" => &self.uppercase,
+            "_T" => &self.title_case,
+            "_F" => &self.first_upper,
+            _ => &self.lowercase,
         }
     }
-
-    word_vec.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.1.cmp(&b.1)));
-    word_vec
 }
 
 #[inline]
@@ -60,36 +77,226 @@ fn log_error(message: &str) {
     console::log_1(&JsValue::from_str(message));
 }
 
-fn build_case_insensitive_regex(words: &[String]) -> Option<Regex> {
-    if words.is_empty() {
-        return None;
+/// The case variants of a field's word, of each of its aliases and of the
+/// parts of a multi-word word.
+#[derive(Debug, Clone)]
+struct FieldEntry {
+    word: FieldVariants,
+    aliases: Vec<FieldVariants>,
+    parts: Vec<(NamePart, FieldVariants)>,
+    case_sensitive: bool,
+    locale: Locale,
+}
+
+impl FieldEntry {
+    fn new(entry: &MaskEntry, locale: Locale) -> Self {
+        let variants = |word: &str| {
+            if entry.case_sensitive {
+                FieldVariants::exact(word)
+            } else {
+                FieldVariants::new(word, locale)
+            }
+        };
+
+        FieldEntry {
+            word: variants(&entry.word),
+            aliases: entry.aliases.iter().map(|alias| variants(alias)).collect(),
+            parts: NamePart::ALL
+                .iter()
+                .filter_map(|part| Some((*part, variants(part.of(&entry.word)?))))
+                .collect(),
+            case_sensitive: entry.case_sensitive,
+            locale,
+        }
     }
 
-    let pattern = words
-        .iter()
-        .map(|word| regex::escape(word))
-        .collect::<Vec<_>>()
-        .join("|");
+    /// Returns the case suffix that decodes back to exactly `matched`.
+    ///
+    /// The plain, `_F` and `_A` suffixes are preferred; other casings fall
+    /// back to a `_M` mask. Returns `None` when no suffix can reproduce the
+    /// occurrence, e.g. for inflected forms.
+    fn exact_case_suffix(
+        &self,
+        alias: Option<usize>,
+        part: Option<NamePart>,
+        matched: &str,
+    ) -> Option<String> {
+        let variants = self.variants(alias, part);
+        if self.case_sensitive {
+            return (variants.lowercase == matched).then(String::new);
+        }
 
-    RegexBuilder::new(&format!("(?:{})", pattern))
-        .case_insensitive(true)
-        .build()
-        .ok()
+        let simple_suffix = determine_case_suffix(matched);
+        if variants.for_suffix(simple_suffix) == matched {
+            return Some(simple_suffix.to_string());
+        }
+
+        let mixed_suffix = mixed_case_suffix(matched);
+        let mask = &mixed_suffix["_M".len()..];
+        (apply_case_mask(&variants.lowercase, mask, self.locale).as_deref() == Some(matched))
+            .then_some(mixed_suffix)
+    }
+
+    /// Returns the variants for the word, for the given one-based alias, or
+    /// for the given part of the word.
+    fn variants(&self, alias: Option<usize>, part: Option<NamePart>) -> &FieldVariants {
+        if let Some((_, variants)) = self.parts.iter().find(|(listed, _)| Some(*listed) == part) {
+            return variants;
+        }
+
+        alias
+            .and_then(|alias| self.aliases.get(alias - 1))
+            .unwrap_or(&self.word)
+    }
+}
+
+/// Field variants plus any exact placeholders recorded in a mapping.
+struct DecodeTable<'a> {
+    fields: Vec<FieldEntry>,
+    /// Variant placeholders and their original text, longest placeholder first.
+    exact_tokens: Vec<(&'a str, &'a str)>,
+    /// The text before the number of each label's placeholders, with the
+    /// label's field indexes in numbering order, longest stem first.
+    stems: Vec<(String, Vec<usize>)>,
+    format: PlaceholderFormat,
+}
+
+impl<'a> DecodeTable<'a> {
+    fn from_entries(entries: &[MaskEntry], locale: Locale, format: PlaceholderFormat) -> Self {
+        let mut numbers = FieldNumbers::new(&format);
+        for entry in entries {
+            numbers.push(entry.category.as_deref());
+        }
+
+        let mut stems: Vec<(String, Vec<usize>)> = numbers
+            .groups()
+            .iter()
+            .map(|(label, indexes)| (format.stem(label), indexes.clone()))
+            .collect();
+        stems.sort_by_key(|(stem, _)| std::cmp::Reverse(stem.len()));
+
+        DecodeTable {
+            fields: entries
+                .iter()
+                .map(|entry| FieldEntry::new(entry, locale))
+                .collect(),
+            exact_tokens: Vec::new(),
+            stems,
+            format,
+        }
+    }
+
+    fn from_mapping(mapping: &'a FieldMapping) -> Self {
+        let mut table = Self::from_entries(
+            mapping.field_entries(),
+            mapping.case_locale(),
+            mapping.placeholder_format().clone(),
+        );
+        table.exact_tokens = mapping
+            .variants()
+            .iter()
+            .map(|(placeholder, original)| (placeholder.as_str(), original.as_str()))
+            .collect();
+        table
+            .exact_tokens
+            .sort_by_key(|(placeholder, _)| std::cmp::Reverse(placeholder.len()));
+        table
+    }
+
+    fn exact_token_at(&self, text: &str, start: usize) -> Option<(usize, &'a str)> {
+        self.exact_tokens
+            .iter()
+            .find(|(placeholder, _)| {
+                let end = start + placeholder.len();
+                text[start..].starts_with(placeholder)
+                    && !text.as_bytes().get(end).is_some_and(u8::is_ascii_digit)
+            })
+            .map(|(placeholder, original)| (start + placeholder.len(), *original))
+    }
+
+    /// Returns the end of a run at `start` that a checked placeholder of some
+    /// label could have been written as: the stem, its digits and the letter
+    /// after them, if any.
+    fn checked_run_end(&self, text: &str, start: usize) -> Option<usize> {
+        if !self.format.is_checked() {
+            return None;
+        }
+
+        self.stems.iter().find_map(|(stem, _)| {
+            let digits_start = start + stem.len();
+            let digits_len = text[start..]
+                .strip_prefix(stem.as_str())?
+                .bytes()
+                .take_while(u8::is_ascii_digit)
+                .count();
+            let digits_end = digits_start + digits_len;
+            let letter_len = text[digits_end..]
+                .chars()
+                .next()
+                .filter(char::is_ascii_uppercase)
+                .map_or(0, char::len_utf8);
+            (digits_len > 0).then_some(digits_end + letter_len)
+        })
+    }
+
+    /// Whether a placeholder of some label could start at `start`.
+    fn starts_token(&self, text: &str, start: usize) -> bool {
+        self.stems
+            .iter()
+            .any(|(stem, _)| text[start..].starts_with(stem.as_str()))
+    }
+}
+
+/// Rebuilds `text` with every match replaced by the output of `replacement`.
+fn replace_matches(
+    text: &str,
+    matches: &[WordMatch],
+    replacement: impl FnMut(&WordMatch, &str) -> String,
+) -> String {
+    replace_matches_with_gaps(
+        text,
+        matches,
+        |span, output| output.push_str(&text[span]),
+        replacement,
+    )
+}
+
+/// Like [`replace_matches`], but writes the text between matches with `gap`,
+/// which receives its span in `text`.
+fn replace_matches_with_gaps(
+    text: &str,
+    matches: &[WordMatch],
+    mut gap: impl FnMut(Range<usize>, &mut String),
+    mut replacement: impl FnMut(&WordMatch, &str) -> String,
+) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut cursor = 0;
+
+    for word_match in matches {
+        gap(cursor..word_match.start, &mut output);
+        output.push_str(&replacement(
+            word_match,
+            &text[word_match.start..word_match.end],
+        ));
+        cursor = word_match.end;
+    }
+
+    gap(cursor..text.len(), &mut output);
+    output
 }
 
 fn parse_field_number_prefix(
     text: &str,
     start: usize,
+    prefix: &str,
     max_fields: usize,
 ) -> Option<(usize, usize)> {
-    const FIELD_PREFIX: &str = "FIELD_";
-
-    if !text[start..].starts_with(FIELD_PREFIX) {
+    if !text[start..].starts_with(prefix) {
         return None;
     }
 
     let bytes = text.as_bytes();
-    let mut cursor = start + FIELD_PREFIX.len();
+    let mut cursor = start + prefix.len();
     let mut numeric_value = 0usize;
     let mut matched: Option<(usize, usize)> = None;
 
@@ -107,95 +314,357 @@ fn parse_field_number_prefix(
     matched
 }
 
+/// Parses a field number followed by its check character, returning the
+/// position after the check character and the number.
+fn parse_checked_field_number(
+    text: &str,
+    start: usize,
+    prefix: &str,
+    max_fields: usize,
+) -> Option<(usize, usize)> {
+    let digits_start = start + prefix.len();
+    let digits_len = text[start..]
+        .strip_prefix(prefix)?
+        .bytes()
+        .take_while(u8::is_ascii_digit)
+        .count();
+    let digits_end = digits_start + digits_len;
+    let field_num = text[digits_start..digits_end]
+        .parse::<usize>()
+        .ok()
+        .filter(|field_num| (1..=max_fields).contains(field_num))
+        .filter(|_| text.as_bytes()[digits_start] != b'0')?;
+
+    let check = check_character(field_num);
+    text[digits_end..]
+        .starts_with(check)
+        .then_some((digits_end + check.len_utf8(), field_num))
+}
+
+/// Parses a `_Vk` alias suffix at `start`, returning the alias number and the
+/// position after it.
+fn parse_alias_suffix(
+    text: &str,
+    start: usize,
+    format: &PlaceholderFormat,
+) -> Option<(usize, usize)> {
+    let digits_start = format.modifier_end(text, start, "V")?;
+    let digits_len = text[digits_start..]
+        .bytes()
+        .take_while(u8::is_ascii_digit)
+        .count();
+    let alias = text[digits_start..digits_start + digits_len]
+        .parse::<usize>()
+        .ok()
+        .filter(|alias| *alias != 0)?;
+
+    Some((alias, digits_start + digits_len))
+}
+
+/// Parses a `_M` mixed-case suffix at `start` and applies it to `word`,
+/// returning the recased word and the position after the suffix.
+fn parse_mixed_case_suffix(
+    text: &str,
+    start: usize,
+    word: &str,
+    locale: Locale,
+    format: &PlaceholderFormat,
+) -> Option<(String, usize)> {
+    let mask_start = format.modifier_end(text, start, "M")?;
+    let mask_end = mask_start + case_mask_width(word);
+    let mask = text.get(mask_start..mask_end)?;
+    apply_case_mask(word, mask, locale).map(|recased| (recased, mask_end))
+}
+
 fn parse_field_token<'a>(
     text: &str,
     start: usize,
-    field_variants: &'a [FieldVariants],
-) -> Option<(usize, &'a str)> {
-    const FIELD_PREFIX: &str = "FIELD_";
+    table: &'a DecodeTable,
+) -> Option<(usize, Cow<'a, str>)> {
+    if let Some((end, exact)) = table.exact_token_at(text, start) {
+        return Some((end, Cow::Borrowed(exact)));
+    }
 
-    let (mut cursor, field_num) = parse_field_number_prefix(text, start, field_variants.len())?;
+    let format = &table.format;
+    let (stem, (mut cursor, field_index)) = table.stems.iter().find_map(|(stem, indexes)| {
+        let (cursor, field_num) = if format.is_checked() {
+            parse_checked_field_number(text, start, stem, indexes.len())?
+        } else {
+            parse_field_number_prefix(text, start, stem, indexes.len())?
+        };
+        Some((stem, (cursor, indexes[field_num - 1])))
+    })?;
 
     // Keep unknown complete tokens like FIELD_100_A unchanged; only partial-decode numeric
     // prefixes when the trailing digits are literal text rather than an explicit case suffix.
-    let mut digits_end = start + FIELD_PREFIX.len();
+    let mut digits_end = start + stem.len();
     while digits_end < text.len() && text.as_bytes()[digits_end].is_ascii_digit() {
         digits_end += 1;
     }
 
     if digits_end > cursor
-        && (text[digits_end..].starts_with("_A") || text[digits_end..].starts_with("_F"))
+        && (["A", "F", "T", "M", "LAST"]
+            .iter()
+            .any(|code| format.modifier_end(text, digits_end, code).is_some())
+            || parse_alias_suffix(text, digits_end, format).is_some())
     {
         return None;
     }
 
-    let variants = &field_variants[field_num - 1];
-    let mut resolved = variants.lowercase.as_str();
+    // An unknown alias is a variant recorded in a mapping that is not at
+    // hand, so the whole placeholder stays undecoded.
+    let field = &table.fields[field_index];
+    let alias = match parse_alias_suffix(text, cursor, format) {
+        Some((alias, _)) if alias > field.aliases.len() => return None,
+        Some((alias, alias_end)) => {
+            cursor = alias_end;
+            Some(alias)
+        }
+        None => None,
+    };
+    let part = field
+        .parts
+        .iter()
+        .filter(|_| alias.is_none())
+        .find_map(|(part, _)| {
+            let part_end = format.modifier_end(text, cursor, part.code())?;
+            Some((*part, part_end))
+        })
+        .map(|(part, part_end)| {
+            cursor = part_end;
+            part
+        });
+    let variants = field.variants(alias, part);
+    let mut resolved: Cow<'a, str> = Cow::Borrowed(variants.lowercase.as_str());
+
+    let mixed_case = if field.case_sensitive {
+        None
+    } else {
+        parse_mixed_case_suffix(text, cursor, &variants.lowercase, field.locale, format)
+    };
 
-    if text[cursor..].starts_with("_A") {
-        resolved = variants.uppercase.as_str();
-        cursor += 2;
-    } else if text[cursor..].starts_with("_F") {
-        let next_token_start = cursor + 1;
+    if let Some((recased, mask_end)) = mixed_case {
+        resolved = Cow::Owned(recased);
+        cursor = mask_end;
+    } else if let Some(suffix_end) = format.modifier_end(text, cursor, "A") {
+        resolved = Cow::Borrowed(variants.uppercase.as_str());
+        cursor = suffix_end;
+    } else if let Some(suffix_end) = format
+        .modifier_end(text, cursor, "T")
+        .filter(|_| variants.is_phrase())
+    {
+        resolved = Cow::Borrowed(variants.title_case.as_str());
+        cursor = suffix_end;
+    } else if let Some(suffix_end) = format.modifier_end(text, cursor, "F") {
+        let next_token_start = cursor + format.separator().len();
         let has_adjacent_decodable_field =
-            parse_field_token(text, next_token_start, field_variants).is_some();
+            parse_field_token(text, next_token_start, table).is_some();
 
         if !has_adjacent_decodable_field {
-            resolved = variants.first_upper.as_str();
-            cursor += 2;
+            resolved = Cow::Borrowed(variants.first_upper.as_str());
+            cursor = suffix_end;
         }
     }
 
-    Some((cursor, resolved))
+    let closing_end = cursor + format.closing().len();
+    text[cursor..]
+        .starts_with(format.closing())
+        .then_some((closing_end, resolved))
+}
+
+/// Returns the end of `expected` if it starts at `start`, ignoring ASCII case
+/// or treating underscores, hyphens and spaces alike, but not both, so prose
+/// such as `field 1` is not taken for `FIELD_1`.
+fn lenient_prefix_end(text: &str, start: usize, expected: &str) -> Option<usize> {
+    let is_gap = |c: char| matches!(c, '_' | '-' | ' ');
+    let mut cursor = start;
+    let mut case_changed = false;
+    let mut gap_changed = false;
+    for expected_char in expected.chars() {
+        let found = text[cursor..].chars().next()?;
+        if found != expected_char {
+            if found.eq_ignore_ascii_case(&expected_char) {
+                case_changed = true;
+            } else if is_gap(found) && is_gap(expected_char) {
+                gap_changed = true;
+            } else {
+                return None;
+            }
+        }
+        cursor += found.len_utf8();
+    }
+    (!(case_changed && gap_changed)).then_some(cursor)
 }
 
-fn decode_streaming_fields(text: &str, field_variants: &[FieldVariants]) -> String {
+/// Parses a modifier at `start` whose code may be in any case, returning the
+/// uppercase code and the position after it.
+fn lenient_modifier(
+    text: &str,
+    start: usize,
+    format: &PlaceholderFormat,
+) -> Option<(String, usize)> {
+    let code_start = start + format.separator().len();
+    let code_len = text[start..]
+        .strip_prefix(format.separator())?
+        .bytes()
+        .take_while(u8::is_ascii_alphanumeric)
+        .count();
+    let code = text[code_start..code_start + code_len].to_ascii_uppercase();
+
+    let known = match code.as_str() {
+        "A" | "F" | "T" | "FIRST" | "LAST" => true,
+        _ => match code.split_at(code.len().min(1)) {
+            ("V", digits) => !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()),
+            ("M", mask) => !mask.is_empty() && mask.bytes().all(|b| b.is_ascii_hexdigit()),
+            _ => false,
+        },
+    };
+    known.then_some((code, code_start + code_len))
+}
+
+/// Parses a placeholder at `start` that was mangled in a common way, such as
+/// `Field_3`, `FIELD 3`, `FIELD-3` or `field_3_a`, by decoding its canonical
+/// form.
+///
+/// Returns the end of the mangled text, the canonical form and its decoded
+/// text, or `None` when the text is already canonical or does not decode.
+fn parse_lenient_field_token<'a>(
+    text: &str,
+    start: usize,
+    table: &'a DecodeTable,
+) -> Option<(usize, String, Cow<'a, str>)> {
+    let format = &table.format;
+    table.stems.iter().find_map(|(stem, _)| {
+        let mut cursor = lenient_prefix_end(text, start, stem)?;
+        let digits_len = text[cursor..]
+            .bytes()
+            .take_while(u8::is_ascii_digit)
+            .count();
+        if digits_len == 0 {
+            return None;
+        }
+        let mut canonical = format!("{}{}", stem, &text[cursor..cursor + digits_len]);
+        cursor += digits_len;
+
+        if format.is_checked() {
+            let check = text[cursor..]
+                .chars()
+                .next()
+                .filter(char::is_ascii_alphabetic)?;
+            canonical.push(check.to_ascii_uppercase());
+            cursor += check.len_utf8();
+        }
+        while let Some((code, code_end)) = lenient_modifier(text, cursor, format) {
+            canonical.push_str(format.separator());
+            canonical.push_str(&code);
+            cursor = code_end;
+        }
+        cursor = lenient_prefix_end(text, cursor, format.closing())?;
+        canonical.push_str(format.closing());
+
+        if canonical == text[start..cursor] {
+            return None;
+        }
+        let (end, decoded) = parse_field_token(&canonical, 0, table)?;
+        (end == canonical.len()).then_some((cursor, canonical, decoded))
+    })
+}
+
+/// Parses a placeholder of any of the tables at `start`.
+fn parse_any_field_token<'a>(
+    text: &str,
+    start: usize,
+    tables: &'a [DecodeTable],
+) -> Option<(usize, Cow<'a, str>)> {
+    tables
+        .iter()
+        .filter(|table| table.starts_token(text, start))
+        .find_map(|table| parse_field_token(text, start, table))
+}
+
+/// Decodes the placeholders of every table, which may come from separate
+/// sessions, in one pass. Checked placeholders that fail validation are left
+/// as they are and reported.
+///
+/// When `lenient` is set, commonly mangled placeholders are decoded as well
+/// and reported.
+fn decode_streaming_fields(text: &str, tables: &[DecodeTable], lenient: bool) -> DecodeResult {
     let mut decoded = String::with_capacity(text.len());
+    let mut rejected: Vec<String> = Vec::new();
+    let mut lenient_matches: Vec<LenientMatch> = Vec::new();
     let mut cursor = 0;
 
     while cursor < text.len() {
-        if text[cursor..].starts_with("FIELD_") {
-            if let Some((mut next_cursor, replacement)) =
-                parse_field_token(text, cursor, field_variants)
-            {
-                decoded.push_str(replacement);
-                cursor = next_cursor;
+        if let Some(escape) = tables
+            .iter()
+            .find_map(|table| table.format.escape_at(text, cursor))
+        {
+            decoded.push_str(&text[cursor..escape.start]);
+            cursor = escape.end;
+            continue;
+        }
 
-                loop {
-                    if cursor < text.len() && text[cursor..].starts_with("FIELD_") {
+        if let Some((end, canonical, replacement)) = tables
+            .iter()
+            .filter(|_| lenient)
+            .find_map(|table| parse_lenient_field_token(text, cursor, table))
+        {
+            decoded.push_str(&replacement);
+            lenient_matches.push(LenientMatch::new(
+                text[cursor..end].to_string(),
+                canonical,
+                replacement.into_owned(),
+            ));
+            cursor = end;
+            continue;
+        }
+
+        if let Some((mut next_cursor, replacement)) = parse_any_field_token(text, cursor, tables) {
+            decoded.push_str(&replacement);
+            cursor = next_cursor;
+
+            loop {
+                if cursor < text.len() {
+                    if let Some((parsed_end, parsed_replacement)) =
+                        parse_any_field_token(text, cursor, tables)
+                    {
+                        decoded.push_str(&parsed_replacement);
+                        cursor = parsed_end;
+                        next_cursor = parsed_end;
+                        continue;
+                    }
+                }
+
+                if cursor + 1 < text.len() {
+                    let separator = text.as_bytes()[cursor];
+                    if separator == b'_' || separator == b'-' {
                         if let Some((parsed_end, parsed_replacement)) =
-                            parse_field_token(text, cursor, field_variants)
+                            parse_any_field_token(text, cursor + 1, tables)
                         {
-                            decoded.push_str(parsed_replacement);
+                            decoded.push(separator as char);
+                            decoded.push_str(&parsed_replacement);
                             cursor = parsed_end;
                             next_cursor = parsed_end;
                             continue;
                         }
                     }
-
-                    if cursor + 1 < text.len() {
-                        let separator = text.as_bytes()[cursor];
-                        if (separator == b'_' || separator == b'-')
-                            && text[cursor + 1..].starts_with("FIELD_")
-                        {
-                            if let Some((parsed_end, parsed_replacement)) =
-                                parse_field_token(text, cursor + 1, field_variants)
-                            {
-                                decoded.push(separator as char);
-                                decoded.push_str(parsed_replacement);
-                                cursor = parsed_end;
-                                next_cursor = parsed_end;
-                                continue;
-                            }
-                        }
-                    }
-
-                    cursor = next_cursor;
-                    break;
                 }
 
-                continue;
+                cursor = next_cursor;
+                break;
             }
+
+            continue;
+        }
+
+        if let Some(run_end) = tables
+            .iter()
+            .find_map(|table| table.checked_run_end(text, cursor))
+        {
+            rejected.push(text[cursor..run_end].to_string());
+            decoded.push_str(&text[cursor..run_end]);
+            cursor = run_end;
+            continue;
         }
 
         let Some(character) = text[cursor..].chars().next() else {
@@ -205,7 +674,7 @@ fn decode_streaming_fields(text: &str, field_variants: &[FieldVariants]) -> Stri
         cursor += character.len_utf8();
     }
 
-    decoded
+    DecodeResult::new(decoded, rejected, lenient_matches)
 }
 
 /// Masks specified words in text with asterisks.
@@ -219,38 +688,573 @@ fn decode_streaming_fields(text: &str, field_variants: &[FieldVariants]) -> Stri
 ///
 /// The processed text with specified words replaced by asterisks
 pub fn mask_text(text: String, mask_words: &Set) -> String {
-    if text.is_empty() || mask_words.size() == 0 {
+    mask_text_with_options(text, mask_words, &MaskOptions::default())
+}
+
+/// Masks specified words in text with asterisks using the given options.
+///
+/// # Parameters
+///
+/// * `text` - The text to process
+/// * `mask_words` - A JavaScript Set containing words to mask
+/// * `options` - Matching options
+///
+/// # Returns
+///
+/// The processed text with specified words replaced by asterisks
+pub fn mask_text_with_options(text: String, mask_words: &Set, options: &MaskOptions) -> String {
+    mask_words_with_asterisks(&text, mask_words, options)
+        .map(AsteriskResult::into_text)
+        .unwrap_or(text)
+}
+
+/// Masks specified words in text with asterisks and reports every partially
+/// masked occurrence.
+///
+/// # Parameters
+///
+/// * `text` - The text to process
+/// * `mask_words` - A JavaScript Set containing words to mask
+/// * `options` - Matching options
+///
+/// # Returns
+///
+/// The processed text together with the partially masked occurrences
+pub fn mask_text_with_details(
+    text: String,
+    mask_words: &Set,
+    options: &MaskOptions,
+) -> AsteriskResult {
+    mask_words_with_asterisks(&text, mask_words, options)
+        .unwrap_or_else(|| AsteriskResult::new(text, Vec::new()))
+}
+
+/// Replaces matches with asterisks, leaving visible what the entry's or
+/// category's reveal keeps.
+///
+/// Returns `None` when nothing can be masked.
+fn mask_words_with_asterisks(
+    text: &str,
+    mask_words: &Set,
+    options: &MaskOptions,
+) -> Option<AsteriskResult> {
+    if text.is_empty() {
+        return None;
+    }
+
+    let entries = entries_from_set(mask_words, options);
+    if entries.is_empty() && options.context_rules().is_empty() {
+        return None;
+    }
+
+    let Some(matcher) = WordMatcher::new(&entries, options) else {
+        log_error("Unable to compile masking regex for asterisks mode.");
+        return None;
+    };
+
+    let style = options.mask_style();
+    let mut revealed: Vec<(String, Vec<String>)> = Vec::new();
+    let masked = replace_matches(text, &matcher.find_matches(text), |word_match, matched| {
+        let reveal = match entries.get(word_match.word_index) {
+            Some(entry) => entry
+                .reveal
+                .or_else(|| options.category_reveal(entry.category.as_deref())),
+            None => word_match.rule.and_then(|rule| {
+                options.category_reveal(options.context_rules()[rule].category.as_deref())
+            }),
+        };
+
+        if let Some(reveal) = reveal {
+            let (partial, visible) = reveal.apply(matched, style);
+            if !visible.is_empty() {
+                revealed.push((partial.clone(), visible));
+                return partial;
+            }
+        }
+
+        style.hide(matched)
+    });
+
+    Some(AsteriskResult::new(masked, revealed))
+}
+
+/// Collision retries after which a pseudonym is numbered instead.
+const MAX_PSEUDONYM_ATTEMPTS: u32 = 32;
+
+/// Masks specified words in text with pseudonyms recorded in `mapping`.
+///
+/// # Parameters
+///
+/// * `text` - The text to process
+/// * `mask_words` - A JavaScript Set containing words to mask
+/// * `options` - Matching options
+/// * `mapping` - The session mapping, which receives every new pseudonym
+///
+/// # Returns
+///
+/// The processed text with specified words replaced by pseudonyms
+pub fn mask_text_with_pseudonyms(
+    text: String,
+    mask_words: &Set,
+    options: &MaskOptions,
+    mapping: &mut PseudonymMapping,
+) -> String {
+    replace_with_pseudonyms(text, mask_words, options, mapping, false)
+}
+
+/// Masks specified words in text with values of the same shape recorded in
+/// `mapping`.
+///
+/// # Parameters
+///
+/// * `text` - The text to process
+/// * `mask_words` - A JavaScript Set containing words to mask
+/// * `options` - Matching options
+/// * `mapping` - The session mapping, which receives every new replacement
+///
+/// # Returns
+///
+/// The processed text with letters and digits of specified words replaced
+pub fn mask_text_preserving_format(
+    text: String,
+    mask_words: &Set,
+    options: &MaskOptions,
+    mapping: &mut PseudonymMapping,
+) -> String {
+    replace_with_pseudonyms(text, mask_words, options, mapping, true)
+}
+
+/// Replaces matches with pseudonyms, or with values of the same shape when
+/// `preserve_format` is set.
+fn replace_with_pseudonyms(
+    text: String,
+    mask_words: &Set,
+    options: &MaskOptions,
+    mapping: &mut PseudonymMapping,
+    preserve_format: bool,
+) -> String {
+    if text.is_empty() {
         return text;
     }
 
-    let ordered_words: Vec<String> = set_to_sorted_vec(mask_words)
-        .into_iter()
-        .map(|(word, _)| word)
+    let entries = entries_from_set(mask_words, options);
+    if entries.is_empty() && options.context_rules().is_empty() {
+        return text;
+    }
+
+    let Some(matcher) = WordMatcher::new(&entries, options) else {
+        log_error("Unable to compile masking regex for pseudonym mode.");
+        return text;
+    };
+
+    let locale = options.case_locale();
+    let reserved: HashSet<String> = entries
+        .iter()
+        .flat_map(|entry| std::iter::once(&entry.word).chain(&entry.aliases))
+        .map(|term| locale.to_lowercase(term))
         .collect();
+    let lowercase_text = locale.to_lowercase(&text);
+
+    replace_matches(
+        &text,
+        &matcher.find_matches(&text),
+        |word_match, matched| {
+            let entry = entries.get(word_match.word_index);
+            let (listed, category) = match entry {
+                Some(entry) => (
+                    word_match
+                        .alias
+                        .map_or(entry.word.as_str(), |alias| &entry.aliases[alias - 1]),
+                    entry.category.as_deref(),
+                ),
+                None => (
+                    matched,
+                    word_match
+                        .rule
+                        .and_then(|rule| options.context_rules()[rule].category.as_deref()),
+                ),
+            };
+
+            let kind = if preserve_format {
+                PseudonymKind::Other
+            } else {
+                PseudonymKind::of(listed, category)
+            };
+            let full = session_pseudonym(mapping, listed, kind, &reserved, &lowercase_text, locale);
+            let (listed, fake) = word_match
+                .part
+                .and_then(|part| Some((part.of(listed)?, part.of(&full)?)))
+                .unwrap_or((listed, &full));
+
+            let replacement = if preserve_format {
+                cased_per_letter(fake, listed, matched, locale)
+            } else {
+                cased_like(fake, listed, matched, locale)
+            };
+            mapping.add_replacement(replacement.clone(), matched.to_string());
+            replacement
+        },
+    )
+}
+
+/// Returns the pseudonym `mapping` holds for `value`, choosing and recording
+/// one if there is none yet.
+///
+/// A new pseudonym differs from the value, is used by no other value and no
+/// mask word, and does not occur as a whole word in `lowercase_text`, the
+/// lowercased text being masked, so restoring it never rewrites real text.
+fn session_pseudonym(
+    mapping: &mut PseudonymMapping,
+    value: &str,
+    kind: PseudonymKind,
+    reserved: &HashSet<String>,
+    lowercase_text: &str,
+    locale: Locale,
+) -> String {
+    let key = locale.to_lowercase(value);
+    if let Some(assigned) = mapping.assigned(&key) {
+        return assigned.to_string();
+    }
+
+    let is_free = |candidate: &str| {
+        let candidate = locale.to_lowercase(candidate);
+        candidate != key
+            && !reserved.contains(&candidate)
+            && !mapping.is_taken(&candidate)
+            && !contains_whole_word(lowercase_text, &candidate)
+    };
+    let draw = |attempt| pseudonym(value, kind, mapping.key(), mapping.seed(), attempt);
+    let chosen = (0..MAX_PSEUDONYM_ATTEMPTS)
+        .map(draw)
+        .find(|candidate| is_free(candidate))
+        .unwrap_or_else(|| {
+            let base = draw(0);
+            (2..)
+                .map(|number| format!("{} {}", base, number))
+                .find(|candidate| is_free(candidate))
+                .unwrap_or(base)
+        });
+
+    mapping.assign(key, chosen.clone());
+    chosen
+}
+
+/// Whether `word` occurs in `text` with no letter or digit right before or
+/// after it.
+fn contains_whole_word(text: &str, word: &str) -> bool {
+    text.match_indices(word).any(|(start, _)| {
+        let end = start + word.len();
+        !text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric)
+            && !text[end..]
+                .chars()
+                .next()
+                .is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// Writes `fake` in the case of `matched`, an occurrence of `listed`, and
+/// keeps any ending an inflected occurrence adds to `listed`.
+fn cased_like(fake: &str, listed: &str, matched: &str, locale: Locale) -> String {
+    let (head, ending) = split_ending(listed, matched, locale);
+    let cased = match determine_case_suffix(head) {
+        "_A" => locale.to_uppercase(fake),
+        "" => locale.to_lowercase(fake),
+        _ => fake.to_string(),
+    };
+    cased + ending
+}
+
+/// Like [`cased_like`], but gives every letter of `fake` the case of the
+/// letter at the same position in `matched`, so `aB12` stays `xY34`.
+///
+/// Falls back to [`cased_like`] when `fake` and the occurrence differ in
+/// length, as a name part of another length does.
+fn cased_per_letter(fake: &str, listed: &str, matched: &str, locale: Locale) -> String {
+    let (head, ending) = split_ending(listed, matched, locale);
+    if fake.chars().count() != head.chars().count() {
+        return cased_like(fake, listed, matched, locale);
+    }
+
+    let mut cased = String::with_capacity(matched.len());
+    for (fake_char, head_char) in fake.chars().zip(head.chars()) {
+        let fake_char = fake_char.encode_utf8(&mut [0; 4]).to_string();
+        if head_char.is_uppercase() {
+            cased.push_str(&locale.to_uppercase(&fake_char));
+        } else if head_char.is_lowercase() {
+            cased.push_str(&locale.to_lowercase(&fake_char));
+        } else {
+            cased.push_str(&fake_char);
+        }
+    }
+    cased + ending
+}
+
+/// Splits `matched`, an occurrence of `listed`, into the part standing for
+/// `listed` and any ending an inflected occurrence adds to it.
+fn split_ending<'a>(listed: &str, matched: &'a str, locale: Locale) -> (&'a str, &'a str) {
+    let split = matched
+        .char_indices()
+        .nth(listed.chars().count())
+        .map_or(matched.len(), |(index, _)| index);
+    let (head, ending) = matched.split_at(split);
+    if locale.to_lowercase(head) == locale.to_lowercase(listed) {
+        (head, ending)
+    } else {
+        (head, "")
+    }
+}
 
-    if ordered_words.is_empty() {
+/// Replaces every pseudonym recorded in `mapping` that stands as a whole
+/// word with the text it replaced, trying longer pseudonyms first.
+///
+/// # Parameters
+///
+/// * `text` - The text containing pseudonyms
+/// * `mapping` - The mapping the text was masked with
+///
+/// # Returns
+///
+/// The text with recorded pseudonyms replaced by their original text
+pub fn restore_pseudonyms(text: String, mapping: &PseudonymMapping) -> String {
+    let mut replacements: Vec<&(String, String)> = mapping.replacements().iter().collect();
+    if text.is_empty() || replacements.is_empty() {
         return text;
     }
+    replacements.sort_by_key(|(pseudonym, _)| std::cmp::Reverse(pseudonym.len()));
 
-    let Some(pattern) = build_case_insensitive_regex(&ordered_words) else {
-        log_error("Unable to compile masking regex for asterisks mode.");
+    let mut restored = String::with_capacity(text.len());
+    let mut cursor = 0;
+    while cursor < text.len() {
+        let after_word = text[..cursor]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric);
+        let replacement = replacements.iter().find(|(pseudonym, _)| {
+            text[cursor..].starts_with(pseudonym.as_str())
+                && !text[cursor + pseudonym.len()..]
+                    .chars()
+                    .next()
+                    .is_some_and(char::is_alphanumeric)
+        });
+
+        match replacement {
+            Some((pseudonym, original)) if !after_word => {
+                restored.push_str(original);
+                cursor += pseudonym.len();
+            }
+            _ => {
+                let Some(character) = text[cursor..].chars().next() else {
+                    break;
+                };
+                restored.push(character);
+                cursor += character.len_utf8();
+            }
+        }
+    }
+
+    restored
+}
+
+/// Masks specified words in text with keyed tokens recorded in `vault`.
+///
+/// # Parameters
+///
+/// * `text` - The text to process
+/// * `mask_words` - A JavaScript Set containing words to mask
+/// * `options` - Matching options
+/// * `key` - The key tokens are derived with
+/// * `vault` - The vault that receives every token and its word
+///
+/// # Returns
+///
+/// The processed text with specified words replaced by tokens
+pub fn mask_text_with_tokens(
+    text: String,
+    mask_words: &Set,
+    options: &MaskOptions,
+    key: &TokenKey,
+    vault: &mut TokenVault,
+) -> String {
+    if text.is_empty() {
+        return text;
+    }
+
+    let entries = entries_from_set(mask_words, options);
+    if entries.is_empty() && options.context_rules().is_empty() {
+        return text;
+    }
+
+    let Some(matcher) = WordMatcher::new(&entries, options) else {
+        log_error("Unable to compile masking regex for token mode.");
         return text;
     };
 
-    let mut asterisk_masks: HashMap<usize, String> = HashMap::new();
-    pattern
-        .replace_all(&text, |captures: &Captures| {
-            let Some(matched) = captures.get(0) else {
-                return String::new();
+    let locale = options.case_locale();
+    replace_matches(
+        &text,
+        &matcher.find_matches(&text),
+        |word_match, matched| {
+            // Aliases and name parts carry their word's token and a modifier,
+            // whose exact text the vault records. Context rule values, such
+            // as passwords, are kept exactly.
+            let (word, variant, case_sensitive) = match entries.get(word_match.word_index) {
+                Some(entry) => {
+                    let cased = |text: &str| {
+                        if entry.case_sensitive {
+                            text.to_string()
+                        } else {
+                            locale.to_lowercase(text)
+                        }
+                    };
+                    let variant = match (word_match.alias, word_match.part) {
+                        (Some(alias), _) => {
+                            Some((format!("_V{}", alias), cased(&entry.aliases[alias - 1])))
+                        }
+                        (None, Some(part)) => part
+                            .of(&entry.word)
+                            .map(|text| (format!("_{}", part.code()), cased(text))),
+                        (None, None) => None,
+                    };
+                    (cased(&entry.word), variant, entry.case_sensitive)
+                }
+                None => (matched.to_string(), None, true),
             };
 
-            let length = matched.as_str().len();
-            asterisk_masks
-                .entry(length)
-                .or_insert_with(|| "*".repeat(length))
-                .clone()
-        })
-        .to_string()
+            let mut token = key.token(&word);
+            vault.insert(token.clone(), word.clone(), case_sensitive);
+            let written = match variant {
+                Some((modifier, text)) => {
+                    token.push_str(&modifier);
+                    vault.insert(token.clone(), text.clone(), case_sensitive);
+                    text
+                }
+                None => word,
+            };
+
+            let case_suffix = if case_sensitive {
+                String::new()
+            } else {
+                FieldEntry::new(&MaskEntry::new(written), locale)
+                    .exact_case_suffix(None, None, matched)
+                    .unwrap_or_else(|| determine_case_suffix(matched).to_string())
+            };
+            token + &case_suffix
+        },
+    )
+}
+
+/// Decodes tokens recorded in `vault`, leaving unknown ones unchanged.
+///
+/// # Parameters
+///
+/// * `text` - The text containing tokens
+/// * `vault` - The vault holding the word behind every token
+/// * `options` - The options the text was masked with
+///
+/// # Returns
+///
+/// The text with every known token replaced by its word
+pub fn decode_tokens(text: String, vault: &TokenVault, options: &MaskOptions) -> String {
+    if text.is_empty() || !text.contains(TOKEN_PREFIX) {
+        return text;
+    }
+
+    let locale = options.case_locale();
+    let format = PlaceholderFormat::default();
+    let mut decoded = String::with_capacity(text.len());
+    let mut cursor = 0;
+
+    while cursor < text.len() {
+        let token = vault_token_end(&text, cursor, vault)
+            .map(|end| vault_variant_end(&text, cursor, end, vault).unwrap_or(end))
+            .and_then(|end| Some((end, vault.entry_for(&text[cursor..end])?)));
+        let Some((mut end, (word, case_sensitive))) = token else {
+            let Some(character) = text[cursor..].chars().next() else {
+                break;
+            };
+            decoded.push(character);
+            cursor += character.len_utf8();
+            continue;
+        };
+
+        if case_sensitive {
+            decoded.push_str(word);
+        } else {
+            let variants = FieldVariants::new(word, locale);
+            let case_suffix = ["A", "T", "F"]
+                .iter()
+                .find_map(|code| Some((format.modifier_end(&text, end, code)?, *code)));
+            if let Some((recased, mask_end)) =
+                parse_mixed_case_suffix(&text, end, &variants.lowercase, locale, &format)
+            {
+                decoded.push_str(&recased);
+                end = mask_end;
+            } else if let Some((suffix_end, code)) = case_suffix {
+                decoded.push_str(variants.for_suffix(&format!("_{}", code)));
+                end = suffix_end;
+            } else {
+                decoded.push_str(&variants.lowercase);
+            }
+        }
+        cursor = end;
+    }
+
+    decoded
+}
+
+/// Rewrites every token recorded in `vault` as the token of the same word
+/// under `new_key`, recording the new tokens in `vault`.
+///
+/// # Parameters
+///
+/// * `text` - The text containing tokens
+/// * `vault` - The vault holding the word behind every token
+/// * `new_key` - The key to rotate to
+///
+/// # Returns
+///
+/// The text with tokens rewritten under the new key
+pub fn rotate_token_key(text: String, vault: &mut TokenVault, new_key: &TokenKey) -> String {
+    if text.is_empty() || !text.contains(TOKEN_PREFIX) {
+        return text;
+    }
+
+    let mut rotated = String::with_capacity(text.len());
+    let mut cursor = 0;
+
+    while cursor < text.len() {
+        let known = vault_token_end(&text, cursor, vault).and_then(|end| {
+            let (word, case_sensitive) = vault.entry_for(&text[cursor..end])?;
+            Some((end, word.to_string(), case_sensitive))
+        });
+        if let Some((end, word, case_sensitive)) = known {
+            let token = new_key.token(&word);
+            rotated.push_str(&token);
+            vault.insert(token.clone(), word, case_sensitive);
+            if let Some(variant_end) = vault_variant_end(&text, cursor, end, vault) {
+                let modifier = &text[end..variant_end];
+                let variant = vault
+                    .word_text(&text[cursor..variant_end])
+                    .map(str::to_string);
+                if let Some(variant) = variant {
+                    vault.insert(token + modifier, variant, case_sensitive);
+                }
+            }
+            cursor = end;
+            continue;
+        }
+
+        let Some(character) = text[cursor..].chars().next() else {
+            break;
+        };
+        rotated.push(character);
+        cursor += character.len_utf8();
+    }
+
+    rotated
 }
 
 /// Masks specified words in text with field placeholders.
@@ -271,44 +1275,202 @@ pub fn mask_text_with_fields(text: String, mask_words: &Set) -> String {
         return text;
     }
 
-    let word_vec = set_to_sorted_vec(mask_words);
-    if word_vec.is_empty() {
-        return text;
+    let entries = set_to_sorted_vec(mask_words);
+    mask_words_with_fields(&text, &entries, &MaskOptions::default(), None).unwrap_or(text)
+}
+
+/// Masks specified words with field placeholders and records a mapping.
+///
+/// Occurrences that the `_F`/`_A` suffixes cannot reproduce, such as
+/// inflected forms, receive a `FIELD_N_Vk` placeholder whose exact original
+/// text is stored in the mapping.
+///
+/// # Parameters
+///
+/// * `text` - The text to process
+/// * `mask_words` - A JavaScript Set containing words to mask
+/// * `options` - Matching options
+///
+/// # Returns
+///
+/// The masked text together with the mapping needed to decode it
+pub fn mask_text_with_fields_and_mapping(
+    text: String,
+    mask_words: &Set,
+    options: &MaskOptions,
+) -> MaskResult {
+    let entries = entries_from_set(mask_words, options);
+
+    let mut generated;
+    let options = if options.namespaces_sessions() && options.session_namespace().is_none() {
+        generated = options.clone();
+        generated.set_session_namespace(Some(random_namespace()));
+        &generated
+    } else {
+        options
+    };
+
+    let mut mapping = FieldMapping::new();
+    mapping.set_case_locale(options.case_locale());
+    mapping.set_placeholder_format(field_format(&entries, options));
+    if !options.unlinks_placeholders() {
+        for entry in &entries {
+            mapping.push_field(entry);
+        }
     }
 
-    let ordered_words: Vec<String> = word_vec.iter().map(|(word, _)| word.clone()).collect();
+    if text.is_empty() || (entries.is_empty() && options.context_rules().is_empty()) {
+        let escaped = mapping.placeholder_format().escape(&text).into_owned();
+        return MaskResult::new(escaped, mapping);
+    }
+
+    let masked =
+        mask_words_with_fields(&text, &entries, options, Some(&mut mapping)).unwrap_or(text);
+    MaskResult::new(masked, mapping)
+}
+
+/// The placeholder format of `options`, with the categories of `entries`
+/// and of the context rules in use as labels.
+fn field_format(entries: &[MaskEntry], options: &MaskOptions) -> PlaceholderFormat {
+    let rule_categories = options.context_rules().iter().map(|rule| &rule.category);
+    options.placeholder_format().with_labels(
+        entries
+            .iter()
+            .map(|entry| &entry.category)
+            .chain(rule_categories)
+            .map(Option::as_deref),
+    )
+}
+
+/// Replaces matches with field placeholders.
+///
+/// Alias occurrences carry a `_Vk` suffix naming the alias, and parts of a
+/// multi-word word carry `_FIRST` or `_LAST`. When a mapping is given, alias
+/// placeholders are recorded in it, and occurrences that no suffix can
+/// reproduce get a fresh variant number past the aliases. Values found by
+/// context rules are appended to the mapping as new fields.
+///
+/// With unlinkable placeholders and a mapping, every occurrence is instead
+/// appended as a new field linked to its value's first field.
+fn mask_words_with_fields(
+    text: &str,
+    entries: &[MaskEntry],
+    options: &MaskOptions,
+    mut mapping: Option<&mut FieldMapping>,
+) -> Option<String> {
+    if entries.is_empty() && options.context_rules().is_empty() {
+        return None;
+    }
 
-    let Some(pattern) = build_case_insensitive_regex(&ordered_words) else {
+    let Some(matcher) = WordMatcher::new(entries, options) else {
         log_error("Unable to compile masking regex for field mode.");
-        return text;
+        return None;
     };
 
-    let mut field_by_lowercase: HashMap<String, usize> =
-        HashMap::with_capacity(ordered_words.len());
-    for (index, word) in ordered_words.iter().enumerate() {
-        field_by_lowercase.insert(word.to_lowercase(), index + 1);
+    let locale = options.case_locale();
+    let format = &field_format(entries, options);
+    let mut fields: Vec<FieldEntry> = entries
+        .iter()
+        .map(|entry| FieldEntry::new(entry, locale))
+        .collect();
+    let mut numbers = FieldNumbers::new(format);
+    for entry in entries {
+        numbers.push(entry.category.as_deref());
     }
+    let mut variant_tokens: HashMap<(usize, String), String> = HashMap::new();
+    let mut variant_counts: Vec<usize> = fields.iter().map(|field| field.aliases.len()).collect();
+    let unlinkable = options.unlinks_placeholders() && mapping.is_some();
+    let mut values: Vec<MaskEntry> = Vec::new();
+    let mut occurrences = FieldNumbers::new(format);
+    let mut occurrence_count = 0;
+    let mut first_fields: Vec<Option<usize>> = vec![None; entries.len()];
 
-    pattern
-        .replace_all(&text, |captures: &Captures| {
-            let Some(matched) = captures.get(0) else {
-                return String::new();
-            };
+    // Matching runs on the original text, so only the text between matches
+    // is escaped.
+    let escape_gap = |span, output: &mut String| format.push_escaped(text, span, output);
+    let matches = matcher.find_matches(text);
+    let masked = replace_matches_with_gaps(text, &matches, escape_gap, |word_match, matched| {
+        if word_match.word_index == fields.len() {
+            let mut value = MaskEntry::new(matched.to_string());
+            value.category = word_match
+                .rule
+                .and_then(|rule| options.context_rules()[rule].category.clone());
+            fields.push(FieldEntry::new(&value, locale));
+            numbers.push(value.category.as_deref());
+            variant_counts.push(0);
+            first_fields.push(None);
+            if let Some(mapping) = mapping.as_deref_mut().filter(|_| !unlinkable) {
+                mapping.push_field(&value);
+            }
+            values.push(value);
+        }
 
-            let matched_word = matched.as_str();
-            let field_num = field_by_lowercase
-                .get(&matched_word.to_lowercase())
-                .copied()
-                .unwrap_or(0);
+        let occurrence = match mapping.as_deref_mut().filter(|_| unlinkable) {
+            Some(mapping) => {
+                let entry = entries
+                    .get(word_match.word_index)
+                    .unwrap_or_else(|| &values[word_match.word_index - entries.len()]);
+                mapping.push_field(entry);
+                let field_number = mapping.field_entries().len();
+                match first_fields[word_match.word_index] {
+                    Some(first) => mapping.link_fields(field_number, first),
+                    None => first_fields[word_match.word_index] = Some(field_number),
+                }
+                occurrences.push(entry.category.as_deref());
+                occurrence_count += 1;
+                Some(occurrence_count - 1)
+            }
+            None => None,
+        };
+        let (label, field_num) = match occurrence {
+            Some(occurrence) => occurrences.get(occurrence),
+            None => numbers.get(word_match.word_index),
+        };
+        let field = &fields[word_match.word_index];
+        let exact_suffix = field.exact_case_suffix(word_match.alias, word_match.part, matched);
+        let case_suffix = match &exact_suffix {
+            Some(suffix) => suffix.as_str(),
+            None if field.case_sensitive => "",
+            None => determine_case_suffix(matched),
+        };
+        let alias_code = word_match.alias.map(|alias| format!("V{}", alias));
+        let form_code = match (&alias_code, word_match.part) {
+            (Some(alias_code), _) => alias_code.as_str(),
+            (None, Some(part)) => part.code(),
+            (None, None) => "",
+        };
+        let placeholder = format.render(label, field_num, &[form_code, case_code(case_suffix)]);
+
+        let Some(mapping) = mapping.as_deref_mut() else {
+            return placeholder;
+        };
 
-            if field_num == 0 {
-                return matched_word.to_string();
+        if exact_suffix.is_some() {
+            if word_match.alias.is_some() {
+                mapping.add_variant(placeholder.clone(), matched.to_string());
             }
+            return placeholder;
+        }
 
-            let case_suffix = determine_case_suffix(matched_word);
-            format!("FIELD_{}{}", field_num, case_suffix)
-        })
-        .to_string()
+        if unlinkable {
+            let placeholder = format.render(label, field_num, &["V1"]);
+            mapping.add_variant(placeholder.clone(), matched.to_string());
+            return placeholder;
+        }
+
+        variant_tokens
+            .entry((word_match.word_index, matched.to_string()))
+            .or_insert_with(|| {
+                variant_counts[word_match.word_index] += 1;
+                let variant_code = format!("V{}", variant_counts[word_match.word_index]);
+                let placeholder = format.render(label, field_num, &[&variant_code]);
+                mapping.add_variant(placeholder.clone(), matched.to_string());
+                placeholder
+            })
+            .clone()
+    });
+
+    Some(masked)
 }
 
 /// Decodes text that was previously masked with field placeholders.
@@ -322,30 +1484,123 @@ pub fn mask_text_with_fields(text: String, mask_words: &Set) -> String {
 ///
 /// The decoded text with field placeholders replaced by their original words
 pub fn decode_obfuscated_text(text: String, mask_words: &Set) -> String {
+    decode_obfuscated_text_with_options(text, mask_words, &MaskOptions::default())
+}
+
+/// Decodes text that was previously masked with field placeholders using the
+/// given options.
+///
+/// # Parameters
+///
+/// * `text` - The text with field placeholders to decode
+/// * `mask_words` - A JavaScript Set containing the original words
+/// * `options` - The options the text was masked with
+///
+/// # Returns
+///
+/// The decoded text with field placeholders replaced by their original words
+pub fn decode_obfuscated_text_with_options(
+    text: String,
+    mask_words: &Set,
+    options: &MaskOptions,
+) -> String {
     if text.is_empty() || mask_words.size() == 0 {
         return text;
     }
 
-    if !text.contains("FIELD_") {
+    if !text.contains(options.placeholder_format().prefix()) {
         return text;
     }
 
-    let word_vec = set_to_sorted_vec(mask_words);
-    if word_vec.is_empty() {
+    let entries = entries_from_set(mask_words, options);
+    if entries.is_empty() {
         return text;
     }
 
-    let field_variants = word_vec
-        .into_iter()
-        .map(|(word, _)| {
-            let lowercase = word.to_lowercase();
-            FieldVariants {
-                uppercase: word.to_uppercase(),
-                first_upper: capitalize_first(&lowercase),
-                lowercase,
-            }
-        })
-        .collect::<Vec<_>>();
+    let format = field_format(&entries, options);
+    let table = DecodeTable::from_entries(&entries, options.case_locale(), format);
+    decode_streaming_fields(&text, std::slice::from_ref(&table), false).into_text()
+}
+
+/// Decodes text that mixes documents masked in several sessions, using the
+/// mapping of each.
+///
+/// # Parameters
+///
+/// * `text` - The text with field placeholders to decode
+/// * `mappings` - The mappings returned by the masking passes
+///
+/// # Returns
+///
+/// The decoded text with the placeholders of every session replaced by their
+/// original text
+pub fn decode_with_mappings(text: String, mappings: &[FieldMapping]) -> String {
+    if text.is_empty()
+        || !mappings
+            .iter()
+            .any(|mapping| text.contains(mapping.placeholder_format().prefix()))
+    {
+        return text;
+    }
+
+    let tables: Vec<DecodeTable> = mappings.iter().map(DecodeTable::from_mapping).collect();
+    decode_streaming_fields(&text, &tables, false).into_text()
+}
+
+/// Decodes text using the mapping recorded when it was masked.
+///
+/// # Parameters
+///
+/// * `text` - The text with field placeholders to decode
+/// * `mapping` - The mapping returned by the masking pass
+///
+/// # Returns
+///
+/// The decoded text with every placeholder replaced by its original text
+pub fn decode_with_mapping(text: String, mapping: &FieldMapping) -> String {
+    if text.is_empty() || !text.contains(mapping.placeholder_format().prefix()) {
+        return text;
+    }
+
+    decode_with_details(text, mapping).into_text()
+}
+
+/// Decodes text using the mapping recorded when it was masked and reports
+/// the placeholders it left undecoded.
+///
+/// # Parameters
+///
+/// * `text` - The text with field placeholders to decode
+/// * `mapping` - The mapping returned by the masking pass
+///
+/// # Returns
+///
+/// The decoded text together with every checked placeholder that failed
+/// validation
+pub fn decode_with_details(text: String, mapping: &FieldMapping) -> DecodeResult {
+    if text.is_empty() || !text.contains(mapping.placeholder_format().prefix()) {
+        return DecodeResult::new(text, Vec::new(), Vec::new());
+    }
+
+    decode_streaming_fields(&text, &[DecodeTable::from_mapping(mapping)], false)
+}
+
+/// Decodes text using the mapping recorded when it was masked, also
+/// accepting placeholders that were mangled in common ways.
+///
+/// # Parameters
+///
+/// * `text` - The text with field placeholders to decode
+/// * `mapping` - The mapping returned by the masking pass
+///
+/// # Returns
+///
+/// The decoded text together with every mangled placeholder it accepted and
+/// every checked placeholder that failed validation
+pub fn decode_leniently(text: String, mapping: &FieldMapping) -> DecodeResult {
+    if text.is_empty() {
+        return DecodeResult::new(text, Vec::new(), Vec::new());
+    }
 
-    decode_streaming_fields(&text, &field_variants)
+    decode_streaming_fields(&text, &[DecodeTable::from_mapping(mapping)], true)
 }
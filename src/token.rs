@@ -0,0 +1,203 @@
+//! Keyed tokens that stay the same across sessions.
+//!
+//! A token such as `TOK_3f9a2c` is the start of the hex HMAC-SHA256 of a
+//! word under a secret key, so every document masked with the same key gives
+//! a word the same token. Aliases and name parts carry their word's token
+//! followed by a modifier, as in `TOK_3f9a2c_V1` or `TOK_3f9a2c_LAST`. The
+//! vault records the word behind every token, and the exact text behind
+//! every token with a modifier, and is all that decoding needs; the key is
+//! only needed to mask.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use wasm_bindgen::prelude::*;
+
+/// The text every token starts with.
+pub(crate) const TOKEN_PREFIX: &str = "TOK_";
+/// The fewest hex digits a token is written with.
+const MIN_DIGITS: usize = 6;
+/// The hex digits in a full HMAC-SHA256.
+const MAX_DIGITS: usize = 64;
+
+/// The secret tokens are derived from.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct TokenKey {
+    secret: String,
+    digits: usize,
+}
+
+#[wasm_bindgen]
+impl TokenKey {
+    /// Creates a key from a secret shared by everyone whose tokens must
+    /// match.
+    #[wasm_bindgen(constructor)]
+    pub fn new(secret: String) -> TokenKey {
+        TokenKey {
+            secret,
+            digits: MIN_DIGITS,
+        }
+    }
+
+    /// The number of hex digits tokens are written with.
+    #[wasm_bindgen(getter)]
+    pub fn digits(&self) -> usize {
+        self.digits
+    }
+
+    /// Sets the number of hex digits tokens are written with, between 6 and
+    /// 64. More digits make it less likely that two words share a token.
+    #[wasm_bindgen(setter)]
+    pub fn set_digits(&mut self, digits: usize) {
+        self.digits = digits.clamp(MIN_DIGITS, MAX_DIGITS);
+    }
+}
+
+impl TokenKey {
+    /// Returns the token for `word`, which depends only on the secret, the
+    /// word and the number of digits.
+    pub(crate) fn token(&self, word: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC takes keys of any length");
+        mac.update(word.as_bytes());
+        let hex: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        format!("{}{}", TOKEN_PREFIX, &hex[..self.digits])
+    }
+}
+
+/// The word behind every token emitted with any key.
+///
+/// Vaults keep the tokens of earlier keys, so text masked before a key
+/// rotation still decodes.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default)]
+pub struct TokenVault {
+    /// Every token with its word and whether the word is case-sensitive.
+    entries: Vec<(String, String, bool)>,
+    /// Tokens that were emitted for more than one word.
+    collisions: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl TokenVault {
+    /// Creates an empty vault.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> TokenVault {
+        TokenVault::default()
+    }
+
+    /// Records the word behind a token, which decodes in the case its
+    /// suffix gives.
+    pub fn add(&mut self, token: String, word: String) {
+        self.insert(token, word, false);
+    }
+
+    /// Records a case-sensitive word behind a token, which always decodes
+    /// exactly as given.
+    pub fn add_case_sensitive(&mut self, token: String, word: String) {
+        self.insert(token, word, true);
+    }
+
+    /// The tokens in the order they were recorded.
+    pub fn tokens(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|(token, _, _)| token.clone())
+            .collect()
+    }
+
+    /// Returns the word behind a token.
+    pub fn word(&self, token: &str) -> Option<String> {
+        self.word_text(token).map(str::to_string)
+    }
+
+    /// The tokens that were emitted for more than one word, which decode to
+    /// the word recorded first. Masking with more digits avoids them.
+    pub fn collisions(&self) -> Vec<String> {
+        self.collisions.clone()
+    }
+
+    /// Whether the word behind a token is case-sensitive.
+    pub fn is_case_sensitive(&self, token: &str) -> bool {
+        self.entry(token)
+            .is_some_and(|(_, _, case_sensitive)| *case_sensitive)
+    }
+}
+
+impl TokenVault {
+    /// The word behind a token and whether it is case-sensitive.
+    pub(crate) fn entry_for(&self, token: &str) -> Option<(&str, bool)> {
+        self.entry(token)
+            .map(|(_, word, case_sensitive)| (word.as_str(), *case_sensitive))
+    }
+
+    /// Records the word behind a token, or the collision when the token
+    /// already stands for another word.
+    pub(crate) fn insert(&mut self, token: String, word: String, case_sensitive: bool) {
+        match self.word_text(&token) {
+            None => self.entries.push((token, word, case_sensitive)),
+            Some(listed) if listed != word && !self.collisions.contains(&token) => {
+                self.collisions.push(token)
+            }
+            Some(_) => {}
+        }
+    }
+
+    pub(crate) fn word_text(&self, token: &str) -> Option<&str> {
+        self.entry(token).map(|(_, word, _)| word.as_str())
+    }
+
+    fn entry(&self, token: &str) -> Option<&(String, String, bool)> {
+        self.entries.iter().find(|(listed, _, _)| listed == token)
+    }
+}
+
+/// Returns the end of the alias or name part modifier that follows the token
+/// ending at `token_end`, if the vault records the token with it.
+pub(crate) fn vault_variant_end(
+    text: &str,
+    start: usize,
+    token_end: usize,
+    vault: &TokenVault,
+) -> Option<usize> {
+    let rest = &text[token_end..];
+    let modifier_len = ["_FIRST", "_LAST"]
+        .iter()
+        .find(|code| rest.starts_with(*code))
+        .map(|code| code.len())
+        .or_else(|| {
+            let digits = rest
+                .strip_prefix("_V")?
+                .bytes()
+                .take_while(u8::is_ascii_digit)
+                .count();
+            (digits > 0).then_some("_V".len() + digits)
+        })?;
+
+    let end = token_end + modifier_len;
+    vault.word_text(&text[start..end]).is_some().then_some(end)
+}
+
+/// Returns the end of the token recorded in `vault` that starts at `start`,
+/// trying the longest run of hex digits first.
+pub(crate) fn vault_token_end(text: &str, start: usize, vault: &TokenVault) -> Option<usize> {
+    let digits_start = start + TOKEN_PREFIX.len();
+    if !text[start..].starts_with(TOKEN_PREFIX) {
+        return None;
+    }
+
+    let run = text[digits_start..]
+        .bytes()
+        .take_while(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+        .count();
+    (MIN_DIGITS..=run)
+        .rev()
+        .map(|digits| digits_start + digits)
+        .find(|end| vault.word_text(&text[start..*end]).is_some())
+}
@@ -0,0 +1,329 @@
+//! Conversion of the JavaScript word list into mask entries.
+//!
+//! Each item of the word list Set is either a plain string or an object of
+//! the form `{ word: "Robert", aliases: ["Bob", "R. Smith"], caseSensitive:
+//! false, category: "name", keepFirst: 1, keepLast: 0, keepDomain: true,
+//! keepCountryCode: false }`. Aliases share the field number of their
+//! canonical word, case-sensitive entries only match their exact spelling,
+//! and the category ranks overlapping matches under the priority policy and
+//! labels placeholders written with a `{category}` template. The `keep`
+//! properties leave parts of the entry visible when masking with asterisks.
+
+use js_sys::{Array, Reflect, Set};
+use std::collections::{HashMap, HashSet};
+use wasm_bindgen::JsValue;
+
+use crate::options::MaskOptions;
+use crate::reveal::Reveal;
+
+/// A word to mask together with the aliases that stand for the same entity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MaskEntry {
+    pub word: String,
+    pub aliases: Vec<String>,
+    /// One-based indexes of the aliases that only match as whole words in
+    /// their exact case, such as derived initials.
+    pub exact_aliases: Vec<usize>,
+    pub case_sensitive: bool,
+    pub category: Option<String>,
+    /// What stays visible when masking with asterisks, if the entry sets it.
+    pub reveal: Option<Reveal>,
+    /// Position of the item in the word list, before sorting by length.
+    pub list_index: usize,
+}
+
+impl MaskEntry {
+    pub(crate) fn new(word: String) -> Self {
+        MaskEntry {
+            word,
+            aliases: Vec::new(),
+            exact_aliases: Vec::new(),
+            case_sensitive: false,
+            category: None,
+            reveal: None,
+            list_index: 0,
+        }
+    }
+
+    /// The key under which the entry's word or alias is deduplicated.
+    pub(crate) fn dedupe_key(&self, term: &str) -> String {
+        if self.case_sensitive {
+            term.to_string()
+        } else {
+            term.to_lowercase()
+        }
+    }
+}
+
+/// The first or last word of a multi-word entry, which can be masked with a
+/// placeholder linked to the entry's field, such as `FIELD_1_LAST`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NamePart {
+    First,
+    Last,
+}
+
+impl NamePart {
+    pub(crate) const ALL: [NamePart; 2] = [NamePart::First, NamePart::Last];
+
+    /// The placeholder modifier naming the part.
+    pub(crate) fn code(self) -> &'static str {
+        match self {
+            NamePart::First => "FIRST",
+            NamePart::Last => "LAST",
+        }
+    }
+
+    /// Returns this part of `phrase`, or `None` when the phrase is a single
+    /// word or the part has fewer than two letters, as for initials.
+    pub(crate) fn of(self, phrase: &str) -> Option<&str> {
+        let mut words = phrase.split_whitespace();
+        let first = words.next()?;
+        let last = words.last()?;
+        let part = match self {
+            NamePart::First => first,
+            NamePart::Last => last,
+        };
+
+        (part.chars().filter(|c| c.is_alphabetic()).count() > 1).then_some(part)
+    }
+}
+
+fn string_property(value: &JsValue, key: &str) -> Option<String> {
+    Reflect::get(value, &JsValue::from_str(key))
+        .ok()
+        .and_then(|property| property.as_string())
+}
+
+fn bool_property(value: &JsValue, key: &str) -> bool {
+    Reflect::get(value, &JsValue::from_str(key))
+        .ok()
+        .and_then(|property| property.as_bool())
+        .unwrap_or(false)
+}
+
+fn count_property(value: &JsValue, key: &str) -> Option<usize> {
+    Reflect::get(value, &JsValue::from_str(key))
+        .ok()
+        .and_then(|property| property.as_f64())
+        .filter(|count| count.is_finite() && *count >= 0.0)
+        .map(|count| count as usize)
+}
+
+/// Reads the `keepFirst`, `keepLast`, `keepDomain` and `keepCountryCode`
+/// properties, if any of them is set.
+fn reveal_property(value: &JsValue) -> Option<Reveal> {
+    let keep_first = count_property(value, "keepFirst");
+    let keep_last = count_property(value, "keepLast");
+    let reveal = Reveal {
+        keep_first: keep_first.unwrap_or(0),
+        keep_last: keep_last.unwrap_or(0),
+        keep_domain: bool_property(value, "keepDomain"),
+        keep_country_code: bool_property(value, "keepCountryCode"),
+    };
+
+    (keep_first.is_some() || keep_last.is_some() || reveal != Reveal::default()).then_some(reveal)
+}
+
+fn string_array_property(value: &JsValue, key: &str) -> Vec<String> {
+    Reflect::get(value, &JsValue::from_str(key))
+        .ok()
+        .filter(Array::is_array)
+        .map(|property| {
+            Array::from(&property)
+                .iter()
+                .filter_map(|item| item.as_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Reads a single word list item, which may be a string or an entry object.
+fn entry_from_js(value: &JsValue) -> Option<MaskEntry> {
+    if let Some(word) = value.as_string() {
+        return Some(MaskEntry::new(word));
+    }
+
+    if !value.is_object() {
+        return None;
+    }
+
+    let word = string_property(value, "word")?;
+    Some(MaskEntry {
+        word,
+        aliases: string_array_property(value, "aliases"),
+        exact_aliases: Vec::new(),
+        case_sensitive: bool_property(value, "caseSensitive"),
+        category: string_property(value, "category").filter(|category| !category.is_empty()),
+        reveal: reveal_property(value),
+        list_index: 0,
+    })
+}
+
+/// Converts a JavaScript Set to a sorted Vec of mask entries.
+///
+/// Empty words are skipped, and a word or alias is dropped when another item
+/// already claimed it. Case-sensitive entries only collide with the exact
+/// same spelling.
+///
+/// # Parameters
+///
+/// * `mask_words` - The JavaScript Set containing words to process
+///
+/// # Returns
+///
+/// A vector of entries, sorted by word length in descending order with ties
+/// kept in insertion order
+pub(crate) fn set_to_sorted_vec(mask_words: &Set) -> Vec<MaskEntry> {
+    let words: Array = Array::from(mask_words);
+    let words_len = words.length();
+
+    if words_len == 0 {
+        return Vec::new();
+    }
+
+    let mut entries: Vec<MaskEntry> = Vec::with_capacity(words_len as usize);
+    let mut seen_keys: HashSet<String> = HashSet::new();
+
+    for i in 0..words_len {
+        let Some(mut entry) = entry_from_js(&words.get(i)) else {
+            continue;
+        };
+
+        if entry.word.is_empty() || !seen_keys.insert(entry.dedupe_key(&entry.word)) {
+            continue;
+        }
+
+        entry.list_index = entries.len();
+        let aliases = std::mem::take(&mut entry.aliases);
+        entry.aliases = aliases
+            .into_iter()
+            .filter(|alias| !alias.is_empty() && seen_keys.insert(entry.dedupe_key(alias)))
+            .collect();
+        entries.push(entry);
+    }
+
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.word.len()));
+    entries
+}
+
+/// Converts the word list like `set_to_sorted_vec`, adding the derived
+/// variants of every entry as aliases when the options ask for them.
+pub(crate) fn entries_from_set(mask_words: &Set, options: &MaskOptions) -> Vec<MaskEntry> {
+    let mut entries = set_to_sorted_vec(mask_words);
+    if options.derive_variants() {
+        add_derived_variants(&mut entries);
+    }
+    entries
+}
+
+/// Appends the derived variants of every entry to its aliases.
+///
+/// A variant is skipped when it is already listed as a word or alias, or
+/// when several entries derive it, such as "J. Doe" from both "Jane Doe" and
+/// "John Doe".
+fn add_derived_variants(entries: &mut [MaskEntry]) {
+    let mut seen_keys: HashSet<String> = entries
+        .iter()
+        .flat_map(|entry| {
+            std::iter::once(&entry.word)
+                .chain(&entry.aliases)
+                .map(move |term| entry.dedupe_key(term))
+        })
+        .collect();
+
+    let derived: Vec<Vec<(String, bool)>> = entries
+        .iter()
+        .map(|entry| derived_variants(&entry.word))
+        .collect();
+    let mut derived_counts: HashMap<String, usize> = HashMap::new();
+    for (entry, variants) in entries.iter().zip(&derived) {
+        for (variant, _) in variants {
+            *derived_counts.entry(entry.dedupe_key(variant)).or_insert(0) += 1;
+        }
+    }
+
+    for (entry, variants) in entries.iter_mut().zip(derived) {
+        for (variant, exact) in variants {
+            let key = entry.dedupe_key(&variant);
+            if derived_counts.get(&key) == Some(&1) && seen_keys.insert(key) {
+                entry.aliases.push(variant);
+                if exact {
+                    entry.exact_aliases.push(entry.aliases.len());
+                }
+            }
+        }
+    }
+}
+
+/// Generates the common derived forms of a name.
+///
+/// For "Jane Doe" these are the initial "J. Doe", the initials "JD", the
+/// usernames "jdoe", "jane.doe", "jane_doe" and "doe.jane", and for a
+/// hyphenated surname such as "Smith-Jones" each half, alone and after the
+/// first name. Every word, single or not, also yields its possessive forms.
+///
+/// # Parameters
+///
+/// * `word` - The mask word as listed by the user
+///
+/// # Returns
+///
+/// The derived forms, which may repeat each other or the word itself, each
+/// with whether it only matches as a whole word in its exact case. Initials
+/// do, so "JD" leaves "JDK" and "adjd" alone.
+fn derived_variants(word: &str) -> Vec<(String, bool)> {
+    let mut variants = Vec::new();
+    let names: Vec<&str> = word
+        .split_whitespace()
+        .filter(|name| name.chars().any(char::is_alphabetic))
+        .collect();
+
+    if let [first, .., last] = names.as_slice() {
+        let handle = |name: &str| -> String {
+            name.chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect()
+        };
+        let initial = |name: &str| name.chars().find(|c| c.is_alphabetic());
+        let (first_handle, last_handle) = (handle(first), handle(last));
+
+        if let Some(first_initial) = initial(first) {
+            variants.push((format!("{}. {}", first_initial, last), false));
+            variants.push((
+                format!("{}{}", first_initial.to_lowercase(), last_handle),
+                false,
+            ));
+        }
+        variants.push((
+            names
+                .iter()
+                .filter_map(|name| initial(name))
+                .flat_map(char::to_uppercase)
+                .collect(),
+            true,
+        ));
+        variants.push((format!("{}.{}", first_handle, last_handle), false));
+        variants.push((format!("{}_{}", first_handle, last_handle), false));
+        variants.push((format!("{}.{}", last_handle, first_handle), false));
+
+        let halves: Vec<&str> = last.split('-').filter(|half| !half.is_empty()).collect();
+        if halves.len() > 1 {
+            for half in halves {
+                variants.push((half.to_string(), false));
+                variants.push((format!("{} {}", first, half), false));
+            }
+        }
+    }
+
+    for apostrophe in ['\'', '’'] {
+        if word.ends_with(['s', 'S']) {
+            variants.push((format!("{}{}", word, apostrophe), false));
+        } else {
+            variants.push((format!("{}{}s", word, apostrophe), false));
+        }
+    }
+
+    variants
+}
@@ -3,15 +3,55 @@
 #![cfg(target_arch = "wasm32")]
 
 extern crate wasm_bindgen_test;
-use js_sys::Set;
+use js_sys::{Array, Object, Reflect, Set};
 use wasm_bindgen::JsValue;
 use wasm_bindgen_test::*;
 
 // Import functions from our crate
-use mask_my_text::{decode_obfuscated_text, mask_text, mask_text_with_fields};
+use mask_my_text::{
+    decode_leniently, decode_obfuscated_text, decode_obfuscated_text_with_options, decode_tokens,
+    decode_with_details, decode_with_mapping, decode_with_mappings, mask_text,
+    mask_text_preserving_format, mask_text_with_details, mask_text_with_fields,
+    mask_text_with_fields_and_mapping, mask_text_with_options, mask_text_with_pseudonyms,
+    mask_text_with_tokens, restore_pseudonyms, rotate_token_key, MaskOptions, PseudonymMapping,
+    TokenKey, TokenVault,
+};
 
 wasm_bindgen_test_configure!(run_in_browser);
 
+/// Builds a `{ word, caseSensitive: true }` word list entry.
+fn case_sensitive_entry(word: &str) -> JsValue {
+    let entry = Object::new();
+    Reflect::set(&entry, &JsValue::from_str("word"), &JsValue::from_str(word)).unwrap();
+    Reflect::set(&entry, &JsValue::from_str("caseSensitive"), &JsValue::TRUE).unwrap();
+    entry.into()
+}
+
+/// Builds a `{ word, category }` word list entry.
+fn category_entry(word: &str, category: &str) -> JsValue {
+    let entry = Object::new();
+    Reflect::set(&entry, &JsValue::from_str("word"), &JsValue::from_str(word)).unwrap();
+    Reflect::set(
+        &entry,
+        &JsValue::from_str("category"),
+        &JsValue::from_str(category),
+    )
+    .unwrap();
+    entry.into()
+}
+
+/// Builds a `{ word, aliases }` word list entry.
+fn alias_entry(word: &str, aliases: &[&str]) -> JsValue {
+    let entry = Object::new();
+    let alias_list: Array = aliases
+        .iter()
+        .map(|alias| JsValue::from_str(alias))
+        .collect();
+    Reflect::set(&entry, &JsValue::from_str("word"), &JsValue::from_str(word)).unwrap();
+    Reflect::set(&entry, &JsValue::from_str("aliases"), &alias_list).unwrap();
+    entry.into()
+}
+
 #[wasm_bindgen_test]
 fn test_mask_text_basic() {
     // Create a test Set with words to mask
@@ -713,3 +753,1580 @@ fn test_decode_obfuscated_text_adjacent_field_chains() {
         "Adjacent field chains should decode without precomputed cartesian maps"
     );
 }
+
+#[wasm_bindgen_test]
+fn test_inflection_english_plural_and_possessive_roundtrip() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("company"));
+
+    let mut options = MaskOptions::new();
+    options.set_inflection_language(Some("en".to_string()));
+
+    let original = "One company, two Companies, the company's logo and the companies' staff.";
+    let result = mask_text_with_fields_and_mapping(original.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "One FIELD_1, two FIELD_1_V1, the FIELD_1_V2 logo and the FIELD_1_V3 staff.",
+        "Inflected forms should share the field number of their base word"
+    );
+
+    let decoded = decode_with_mapping(result.text(), &result.mapping());
+    assert_eq!(
+        decoded, original,
+        "Decoding should restore the inflected surface form that was masked"
+    );
+    assert_eq!(
+        decode_obfuscated_text_with_options(result.text(), &mask_words, &options),
+        "One company, two FIELD_1_V1, the FIELD_1_V2 logo and the FIELD_1_V3 staff.",
+        "Variants unknown without the mapping should stay undecoded"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_inflection_german_stemming() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Kunde"));
+
+    let mut options = MaskOptions::new();
+    options.set_inflection_language(Some("de".to_string()));
+
+    let input = "Der Kunde und die Kunden des Kundes.";
+    let expected = "Der ***** und die ****** des ******.";
+
+    let result = mask_text_with_options(input.to_string(), &mask_words, &options);
+    assert_eq!(
+        result, expected,
+        "German inflections should be matched through their shared stem"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_inflection_keeps_other_words_with_the_same_stem() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Carlos"));
+    mask_words.add(&JsValue::from_str("gato"));
+    let mut options = MaskOptions::new();
+    options.set_inflection_language(Some("es".to_string()));
+    assert_eq!(
+        mask_text_with_options(
+            "Carlos y Carla, carl, los gatos y la gata.".to_string(),
+            &mask_words,
+            &options
+        ),
+        "****** y Carla, carl, los ***** y la gata.",
+        "Only forms of the same inflection should be matched"
+    );
+
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Paris"));
+    let mut options = MaskOptions::new();
+    options.set_inflection_language(Some("fr".to_string()));
+    assert_eq!(
+        mask_text_with_options("Paris, un pari, parier.".to_string(), &mask_words, &options),
+        "*****, un pari, parier.",
+        "A stem should not be matched without its ending"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_inflection_disabled_by_default() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("company"));
+
+    let input = "companies";
+    let result = mask_text_with_options(input.to_string(), &mask_words, &MaskOptions::new());
+    assert_eq!(
+        result, input,
+        "Inflected forms should only be matched when a language is set"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_aliases_share_field_number() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&alias_entry("robert", &["bob", "rsmith@corp"]));
+    mask_words.add(&JsValue::from_str("acme"));
+
+    let original = "Robert (bob, rsmith@corp) works at Acme. BOB agrees.";
+    let masked = mask_text_with_fields(original.to_string(), &mask_words);
+    assert_eq!(
+        masked, "FIELD_1_F (FIELD_1_V1, FIELD_1_V2) works at FIELD_2_F. FIELD_1_V1_A agrees.",
+        "Aliases should mask to their canonical word's field number"
+    );
+
+    let decoded = decode_obfuscated_text(masked, &mask_words);
+    assert_eq!(
+        decoded, original,
+        "Each alias occurrence should decode back to the alias text"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_aliases_roundtrip_with_mapping() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&alias_entry("Robert", &["R. Smith"]));
+
+    let original = "Robert, also known as R. Smith.";
+    let result =
+        mask_text_with_fields_and_mapping(original.to_string(), &mask_words, &MaskOptions::new());
+    assert_eq!(
+        result.text(),
+        "FIELD_1_F, also known as FIELD_1_V1_T.",
+        "Alias occurrences should keep their alias number and exact casing"
+    );
+
+    let decoded = decode_with_mapping(result.text(), &result.mapping());
+    assert_eq!(
+        decoded, original,
+        "Mapping should restore the exact alias text"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_alias_plain_entries_still_supported() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&alias_entry("secret", &[]));
+    mask_words.add(&JsValue::from_str("password"));
+
+    let input = "My secret password";
+    let expected = "My ****** ********";
+
+    let result = mask_text(input.to_string(), &mask_words);
+    assert_eq!(
+        result, expected,
+        "Entry objects and plain strings should mix in one word list"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_allowlist_blocks_matches_inside_terms() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("sam"));
+
+    let allowlist = Set::new(&JsValue::NULL);
+    allowlist.add(&JsValue::from_str("Samsung"));
+    allowlist.add(&JsValue::from_str("sample"));
+
+    let mut options = MaskOptions::new();
+    options.set_allowlist(&allowlist);
+
+    let input = "Sam bought a SAMSUNG phone and a sample of samosas.";
+    let expected = "*** bought a SAMSUNG phone and a sample of ***osas.";
+
+    let result = mask_text_with_options(input.to_string(), &mask_words, &options);
+    assert_eq!(
+        result, expected,
+        "Matches inside allowlisted terms should be left unmasked"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_allowlist_applies_to_field_mode() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("sam"));
+    mask_words.add(&JsValue::from_str("sung"));
+
+    let allowlist = Set::new(&JsValue::NULL);
+    allowlist.add(&JsValue::from_str("samsung"));
+
+    let mut options = MaskOptions::new();
+    options.set_allowlist(&allowlist);
+
+    let original = "Sam sung at Samsung.";
+    let result = mask_text_with_fields_and_mapping(original.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "FIELD_2_F FIELD_1 at Samsung.",
+        "Allowlisted terms should block every mask word they contain"
+    );
+    assert_eq!(
+        decode_with_mapping(result.text(), &result.mapping()),
+        original,
+        "Allowlisted text should survive the roundtrip untouched"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_context_rules_mask_values_with_fields() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("acme"));
+
+    let mut options = MaskOptions::new();
+    options.add_token_rule("password:".to_string(), 1);
+    options.add_delimiter_rule("token=".to_string(), "&".to_string());
+
+    let original = "Login for acme password: Hunter2 token=abc123&user=x";
+    let result = mask_text_with_fields_and_mapping(original.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "Login for FIELD_1 password: FIELD_2_F token=FIELD_3&user=x",
+        "Values after keywords should receive field numbers after the mask words"
+    );
+
+    let decoded = decode_with_mapping(result.text(), &result.mapping());
+    assert_eq!(
+        decoded, original,
+        "Context rule values should decode through the mapping"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_context_rules_reuse_field_for_repeated_value() {
+    let mask_words = Set::new(&JsValue::NULL);
+
+    let mut options = MaskOptions::new();
+    options.add_token_rule("employee id".to_string(), 1);
+
+    let input = "Employee ID e-4471 was confirmed. employee id: e-4471";
+    let result = mask_text_with_fields_and_mapping(input.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "Employee ID FIELD_1 was confirmed. employee id: FIELD_1",
+        "The same value should keep one field number"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_context_rules_respect_word_ends_and_punctuation() {
+    let mask_words = Set::new(&JsValue::NULL);
+
+    let mut options = MaskOptions::new();
+    options.add_token_rule("SSN".to_string(), 1);
+
+    let input = "SSNs are listed. The SSN 123-45-6789 is new.";
+    let result = mask_text_with_fields_and_mapping(input.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "SSNs are listed. The SSN FIELD_1_A is new.",
+        "Keywords should only match whole words"
+    );
+
+    let mut options = MaskOptions::new();
+    options.add_token_rule("SSN:".to_string(), 1);
+    let result = mask_text_with_fields_and_mapping(
+        "Her SSN: 123-45-6789. Done.".to_string(),
+        &mask_words,
+        &options,
+    );
+    assert_eq!(
+        result.text(),
+        "Her SSN: FIELD_1_A. Done.",
+        "Sentence punctuation should not be part of the value"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_context_rules_in_asterisk_mode() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("abc"));
+
+    let mut options = MaskOptions::new();
+    options.add_delimiter_rule("Authorization: Bearer".to_string(), "\n".to_string());
+
+    let input = "Authorization: Bearer abc.def.ghi\nabc";
+    let expected = "Authorization: Bearer ***********\n***";
+
+    let result = mask_text_with_options(input.to_string(), &mask_words, &options);
+    assert_eq!(
+        result, expected,
+        "Context values should be masked whole, ahead of overlapping mask words"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_case_sensitive_entry_only_matches_exact_case() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&case_sensitive_entry("US"));
+    mask_words.add(&JsValue::from_str("acme"));
+
+    let input = "Tell us how ACME uses the US business.";
+    let expected = "Tell us how **** uses the ** business.";
+
+    let result = mask_text(input.to_string(), &mask_words);
+    assert_eq!(
+        result, expected,
+        "Case-sensitive entries should not match other casings"
+    );
+
+    let masked = mask_text_with_fields(input.to_string(), &mask_words);
+    assert_eq!(
+        masked, "Tell us how FIELD_1_A uses the FIELD_2 business.",
+        "Case-sensitive entries should be honored in field mode"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_case_sensitive_entry_decodes_exact_form() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&case_sensitive_entry("iPhone"));
+
+    let original = "My iPhone, not my IPHONE.";
+    let masked = mask_text_with_fields(original.to_string(), &mask_words);
+    assert_eq!(
+        masked, "My FIELD_1, not my IPHONE.",
+        "Case-sensitive entries should use a placeholder without case suffix"
+    );
+
+    assert_eq!(
+        decode_obfuscated_text(masked.clone(), &mask_words),
+        original,
+        "Case-sensitive entries should decode to their exact form"
+    );
+    assert_eq!(
+        decode_obfuscated_text("FIELD_1_A".to_string(), &mask_words),
+        "iPhone",
+        "Case suffixes should not alter case-sensitive entries"
+    );
+
+    let result =
+        mask_text_with_fields_and_mapping(original.to_string(), &mask_words, &MaskOptions::new());
+    assert_eq!(
+        decode_with_mapping(result.text(), &result.mapping()),
+        original,
+        "The mapping should remember which fields are case-sensitive"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_mixed_case_words_roundtrip_exactly() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("mcdonald"));
+    mask_words.add(&JsValue::from_str("iphone"));
+    mask_words.add(&JsValue::from_str("devries"));
+    mask_words.add(&JsValue::from_str("ebay"));
+
+    let original = "McDonald sold an iPhone to DeVries on eBay.";
+    let masked = mask_text_with_fields(original.to_string(), &mask_words);
+    assert_eq!(
+        masked, "FIELD_1_M05 sold an FIELD_3_M02 to FIELD_2_M05 on FIELD_4_M2.",
+        "Mixed-case occurrences should carry a _M case mask"
+    );
+
+    let decoded = decode_obfuscated_text(masked, &mask_words);
+    assert_eq!(
+        decoded, original,
+        "Decoding should reproduce mixed-case words exactly"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_mixed_case_mask_followed_by_text() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("ebay"));
+
+    let original = "eBay123 eBayAccount";
+    let masked = mask_text_with_fields(original.to_string(), &mask_words);
+    assert_eq!(
+        masked, "FIELD_1_M2123 FIELD_1_M2Account",
+        "The case mask should have a fixed width"
+    );
+    assert_eq!(
+        decode_obfuscated_text(masked, &mask_words),
+        original,
+        "Text after a fixed-width case mask should stay literal"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_title_case_phrase_roundtrip() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("new york city"));
+
+    let original = "New York City, NEW YORK CITY, New york city and new york city.";
+    let masked = mask_text_with_fields(original.to_string(), &mask_words);
+    assert_eq!(
+        masked, "FIELD_1_T, FIELD_1_A, FIELD_1_F and FIELD_1.",
+        "Title-cased phrases should use the _T suffix"
+    );
+
+    let decoded = decode_obfuscated_text(masked, &mask_words);
+    assert_eq!(
+        decoded, original,
+        "Title-cased phrases should decode with every word capitalized"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_title_suffix_only_for_phrases() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("acme"));
+
+    let original = "acme_TOKEN and acme_Test";
+    let masked = mask_text_with_fields(original.to_string(), &mask_words);
+    assert_eq!(masked, "FIELD_1_TOKEN and FIELD_1_Test");
+    assert_eq!(
+        decode_obfuscated_text(masked, &mask_words),
+        original,
+        "A _T after a single word should stay literal text"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_turkish_locale_dotted_i() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("istanbul"));
+    let mut options = MaskOptions::new();
+    options.set_locale(Some("tr".to_string()));
+
+    let original = "İstanbul, İSTANBUL and istanbul";
+    let result = mask_text_with_fields_and_mapping(original.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "FIELD_1_F, FIELD_1_A and FIELD_1",
+        "Turkish dotted capital I should match a lowercase i"
+    );
+    assert_eq!(
+        decode_with_mapping(result.text(), &result.mapping()),
+        original,
+        "The mapping should decode with Turkish case mappings"
+    );
+    assert_eq!(
+        decode_obfuscated_text_with_options(result.text(), &mask_words, &options),
+        original,
+        "Stateless decoding should honor the locale option"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_turkish_locale_dotless_i() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("ılık"));
+    let mut options = MaskOptions::new();
+    options.set_locale(Some("tr".to_string()));
+
+    let masked = mask_text_with_options("ILIK ilik".to_string(), &mask_words, &options);
+    assert_eq!(
+        masked, "**** ilik",
+        "Dotless i should match capital I but not dotted i"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_german_locale_sharp_s() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("straße"));
+    let mut options = MaskOptions::new();
+    options.set_locale(Some("de-DE".to_string()));
+
+    let original = "STRASSE und Straße";
+    let result = mask_text_with_fields_and_mapping(original.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "FIELD_1_A und FIELD_1_F",
+        "Sharp s should match its uppercase SS spelling"
+    );
+    assert_eq!(
+        decode_with_mapping(result.text(), &result.mapping()),
+        original,
+        "Sharp s should decode to SS in uppercase"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_greek_locale_accents_and_final_sigma() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Οδυσσέας"));
+    let mut options = MaskOptions::new();
+    options.set_locale(Some("el".to_string()));
+
+    let original = "ΟΔΥΣΣΕΑΣ, Οδυσσέας, οδυσσέας";
+    let result = mask_text_with_fields_and_mapping(original.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "FIELD_1_A, FIELD_1_F, FIELD_1",
+        "Unaccented uppercase Greek should match the accented word"
+    );
+    assert_eq!(
+        decode_with_mapping(result.text(), &result.mapping()),
+        original,
+        "Greek should decode without accents in uppercase and with a final sigma"
+    );
+
+    // Listed with a medial sigma at the end, which should still become "ς".
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Οδυσσέασ"));
+    let result = mask_text_with_fields_and_mapping(original.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "FIELD_1_A, FIELD_1_F, FIELD_1",
+        "Sigmas should be written by their position"
+    );
+    assert_eq!(
+        decode_obfuscated_text_with_options(result.text(), &mask_words, &options),
+        original,
+        "A word-final sigma should decode as ς and others as σ"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_uncased_script_case_suffixes() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("東京tower"));
+    mask_words.add(&JsValue::from_str("東京"));
+
+    let original = "東京Tower, 東京TOWER, 東京tower and 東京";
+    let masked = mask_text_with_fields(original.to_string(), &mask_words);
+    assert_eq!(
+        masked, "FIELD_1_M01, FIELD_1_A, FIELD_1 and FIELD_2",
+        "Letters without case should not affect the case suffix"
+    );
+    assert_eq!(
+        decode_obfuscated_text(masked, &mask_words),
+        original,
+        "Uncased letters should decode unchanged"
+    );
+}
+
+#[cfg(feature = "segmentation")]
+#[wasm_bindgen_test]
+fn test_segmentation_respects_chinese_tokens() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("京大"));
+    mask_words.add(&JsValue::from_str("学习"));
+    let mut options = MaskOptions::new();
+
+    let text = "我在北京大学学习";
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "我在北FIELD_1学FIELD_2",
+        "Without segmentation every substring should match"
+    );
+
+    options.set_segmentation(true);
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "我在北京大学FIELD_2",
+        "A match across the boundary of 北京 and 大学 should be skipped"
+    );
+}
+
+#[cfg(feature = "segmentation")]
+#[wasm_bindgen_test]
+fn test_segmentation_leaves_spaced_text_alone() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("京都"));
+    mask_words.add(&JsValue::from_str("sam"));
+    let mut options = MaskOptions::new();
+    options.set_segmentation(true);
+
+    let masked = mask_text_with_options(
+        "東京都に住んでいます。京都 Samsung".to_string(),
+        &mask_words,
+        &options,
+    );
+    assert_eq!(
+        masked, "東京都に住んでいます。****** ***sung",
+        "Only tokens of unspaced scripts should be checked"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_overlap_policy_longest() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("John Smith"));
+    mask_words.add(&JsValue::from_str("Smith Jones"));
+    let mut options = MaskOptions::new();
+
+    let text = "John Smith Jones";
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "FIELD_2_T Jones",
+        "By default the occurrence that starts first should win"
+    );
+
+    options.set_overlap_policy(Some("longest".to_string()));
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "John FIELD_1_T",
+        "The longest policy should prefer the longer occurrence"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_overlap_policy_first_listed() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Smith"));
+    mask_words.add(&JsValue::from_str("John Smith"));
+    let mut options = MaskOptions::new();
+    options.set_overlap_policy(Some("first-listed".to_string()));
+
+    let result = mask_text_with_fields_and_mapping("John Smith".to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "John FIELD_2_F",
+        "The word listed first should win even when it is shorter"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_overlap_policy_priority() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&category_entry("Project Amber", "project"));
+    mask_words.add(&category_entry("Amber", "name"));
+    mask_words.add(&category_entry("alice", "name"));
+    let mut options = MaskOptions::new();
+    options.add_delimiter_rule("user=".to_string(), "&".to_string());
+    options.set_rule_category("user=", "id".to_string());
+    options.set_overlap_policy(Some("priority".to_string()));
+    let priority: Array = ["name", "id", "project"]
+        .iter()
+        .map(|category| JsValue::from_str(category))
+        .collect();
+    options.set_category_priority(&priority);
+
+    let result = mask_text_with_fields_and_mapping(
+        "Project Amber, user=alice&x".to_string(),
+        &mask_words,
+        &options,
+    );
+    assert_eq!(
+        result.text(),
+        "Project FIELD_2_F, user=FIELD_3&x",
+        "The highest-priority category should win over words and rule values"
+    );
+
+    options.set_overlap_policy(None);
+    let result = mask_text_with_fields_and_mapping(
+        "Project Amber, user=alice&x".to_string(),
+        &mask_words,
+        &options,
+    );
+    assert_eq!(
+        result.text(),
+        "FIELD_1_T, user=FIELD_4&x",
+        "By default rule values should still override words"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_dropped_rule_value_keeps_field_numbers() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("a1 extra long"));
+    let mut options = MaskOptions::new();
+    options.add_token_rule("pw".to_string(), 1);
+    options.set_overlap_policy(Some("longest".to_string()));
+
+    let text = "pw: a1 extra long; pw: b2";
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "pw: FIELD_1; pw: FIELD_2",
+        "Rule values should be numbered after those dropped by overlaps"
+    );
+    assert_eq!(decode_with_mapping(result.text(), &result.mapping()), text);
+}
+
+#[wasm_bindgen_test]
+fn test_merge_overlapping_matches() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("John Smith"));
+    mask_words.add(&JsValue::from_str("Smith Jones"));
+    let mut options = MaskOptions::new();
+    options.set_merge_overlaps(true);
+
+    let text = "Dear John Smith Jones,";
+    assert_eq!(
+        mask_text_with_options(text.to_string(), &mask_words, &options),
+        "Dear ****************,",
+        "Overlapping occurrences should be masked as one span"
+    );
+
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "Dear FIELD_2_V1,",
+        "The merged span should take the winning word's field"
+    );
+    assert_eq!(
+        decode_with_mapping(result.text(), &result.mapping()),
+        text,
+        "The merged span should decode to its exact text"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_flexible_whitespace_phrases() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Acme Holdings Ltd"));
+    let mut options = MaskOptions::new();
+
+    let text = "Acme  Holdings\nLtd and Acme Holdings Ltd";
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "Acme  Holdings\nLtd and FIELD_1_T",
+        "By default only the listed spacing should match"
+    );
+
+    options.set_flexible_whitespace(true);
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "FIELD_1_V1 and FIELD_1_T",
+        "Any run of whitespace should separate the words of a phrase"
+    );
+    assert_eq!(
+        decode_with_mapping(result.text(), &result.mapping()),
+        text,
+        "Decoding should restore the original spacing"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_join_hyphenated_line_breaks() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Acme Holdings"));
+    mask_words.add(&JsValue::from_str("e-mail"));
+    let mut options = MaskOptions::new();
+    options.set_flexible_whitespace(true);
+    options.set_join_hyphenated(true);
+
+    let text = "Acme Hold-\n  ings sent an e-mail";
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "FIELD_1_V1 sent an FIELD_2",
+        "Words hyphenated at a line end should still match"
+    );
+    assert_eq!(
+        decode_with_mapping(result.text(), &result.mapping()),
+        text,
+        "Decoding should restore the hyphenation"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_join_hyphenated_with_long_word_list() {
+    let first_names = [
+        "Alice", "Amelia", "Anna", "Arthur", "Beatrice", "Carla", "Daniel", "Edith", "Felix",
+        "George", "Hannah", "Isaac", "Julia", "Kevin", "Laura", "Martin", "Nathan", "Olivia",
+        "Oscar", "Rachel",
+    ];
+    let surnames = [
+        "Abbott", "Bennett", "Carter", "Dawson", "Fletcher", "Gibson", "Holland", "Kendall",
+        "Saunders", "Wheeler",
+    ];
+    let mask_words = Set::new(&JsValue::NULL);
+    for first_name in first_names {
+        for surname in surnames {
+            mask_words.add(&JsValue::from_str(&format!("{} {}", first_name, surname)));
+        }
+    }
+    let mut options = MaskOptions::new();
+    options.set_flexible_whitespace(true);
+    options.set_join_hyphenated(true);
+
+    let text =
+        "Signed by Beatrice Ken-\n    dall and Mar-\ntin Saunders, wit-\nnessed by Oscar Abbott.";
+    let started = js_sys::Date::now();
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    let elapsed = js_sys::Date::now() - started;
+    assert_eq!(
+        result.text(),
+        "Signed by FIELD_5_V1 and FIELD_22_V1, wit-\nnessed by FIELD_193_T.",
+        "Hyphenated names should match among many words"
+    );
+    assert_eq!(decode_with_mapping(result.text(), &result.mapping()), text);
+    assert!(
+        elapsed < 5000.0,
+        "Joining hyphenated words should stay fast, took {}ms",
+        elapsed
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_name_parts_linked_placeholders() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("John Smith"));
+    mask_words.add(&JsValue::from_str("Jane Smith"));
+    mask_words.add(&JsValue::from_str("Alice"));
+    let mut options = MaskOptions::new();
+    options.set_mask_name_parts(true);
+
+    let text = "John Smith called. Later JOHN said Smith and Jane were out.";
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "FIELD_1_T called. Later FIELD_1_FIRST_A said Smith and FIELD_2_FIRST_F were out.",
+        "Name parts should link to their entry unless they are ambiguous"
+    );
+    assert_eq!(
+        decode_with_mapping(result.text(), &result.mapping()),
+        text,
+        "Linked part placeholders should decode with the mapping"
+    );
+    assert_eq!(
+        decode_obfuscated_text(result.text(), &mask_words),
+        text,
+        "Linked part placeholders should decode without a mapping"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_derived_variants_share_field() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Jane Doe"));
+    let mut options = MaskOptions::new();
+    options.set_derive_variants(true);
+
+    let text =
+        "Jane Doe's login is jdoe, mail jane.doe@example.com, signed J. Doe (JD, not JDK, adjd or jd).";
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "FIELD_1_V7_T login is FIELD_1_V2, mail FIELD_1_V4@example.com, signed FIELD_1_V1_T (FIELD_1_V3_A, not JDK, adjd or jd).",
+        "Derived forms should be masked as aliases, initials only as whole words"
+    );
+    assert_eq!(
+        decode_with_mapping(result.text(), &result.mapping()),
+        text,
+        "Derived forms should decode with the mapping"
+    );
+    assert_eq!(
+        decode_obfuscated_text_with_options(result.text(), &mask_words, &options),
+        text,
+        "Derived forms should decode with the same options"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_derived_variants_hyphenated_surname() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Ana Smith-Jones"));
+    mask_words.add(&JsValue::from_str("Bo Smith-Jones"));
+    let mut options = MaskOptions::new();
+    options.set_derive_variants(true);
+
+    let masked = mask_text_with_options(
+        "Ana Jones met Smith and ana.smithjones".to_string(),
+        &mask_words,
+        &options,
+    );
+    assert_eq!(
+        masked, "********* met Smith and **************",
+        "Surname halves shared by two entries should not be masked"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_placeholder_template_roundtrip() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Alice"));
+    mask_words.add(&JsValue::from_str("Bob"));
+    let mut options = MaskOptions::new();
+    options.set_placeholder_template(Some("[REDACTED_{n}]".to_string()));
+    options.set_suffix_separator(Some(":".to_string()));
+
+    let text = "ALICE met Alice and bob";
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "[REDACTED_1:A] met [REDACTED_1:F] and [REDACTED_2]",
+        "Placeholders should follow the template and separator"
+    );
+    assert_eq!(
+        decode_with_mapping(result.text(), &result.mapping()),
+        text,
+        "The mapping should remember the placeholder format"
+    );
+    assert_eq!(
+        decode_obfuscated_text_with_options(result.text(), &mask_words, &options),
+        text,
+        "Placeholders should decode with the same options"
+    );
+    assert_eq!(
+        decode_obfuscated_text_with_options(
+            "[REDACTED_9] and FIELD_1 stay, [REDACTED_2:F] goes".to_string(),
+            &mask_words,
+            &options
+        ),
+        "[REDACTED_9] and FIELD_1 stay, Bob goes",
+        "Unknown and default-format tokens should stay literal"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_placeholder_template_fallbacks() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Alice"));
+    let mut options = MaskOptions::new();
+    options.set_placeholder_template(Some("⟦{n}⟧".to_string()));
+    assert_eq!(options.placeholder_template(), "⟦{n}⟧");
+
+    let result =
+        mask_text_with_fields_and_mapping("Alice and ALICE".to_string(), &mask_words, &options);
+    assert_eq!(result.text(), "⟦1_F⟧ and ⟦1_A⟧");
+
+    options.set_placeholder_template(Some("{n}".to_string()));
+    assert_eq!(
+        options.placeholder_template(),
+        "FIELD_{n}",
+        "A template without text before the number should fall back to the default"
+    );
+    options.set_suffix_separator(Some(String::new()));
+    assert_eq!(options.suffix_separator(), "_");
+}
+
+#[wasm_bindgen_test]
+fn test_category_placeholders_numbered_per_category() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&category_entry("Alice", "person"));
+    mask_words.add(&category_entry("alice@example.com", "email"));
+    mask_words.add(&category_entry("Bob", "person"));
+    mask_words.add(&JsValue::from_str("Acme"));
+    let mut options = MaskOptions::new();
+    options.set_placeholder_template(Some("{category}_{n}".to_string()));
+    options.add_token_rule("password".to_string(), 1);
+    options.set_rule_category("password", "secret".to_string());
+
+    let text = "Alice <alice@example.com> and Bob joined Acme, password: hunter2";
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "PERSON_1_F <EMAIL_1> and PERSON_2_F joined FIELD_1_F, password: SECRET_1",
+        "Each category should be labeled and numbered on its own"
+    );
+    assert_eq!(
+        decode_with_mapping(result.text(), &result.mapping()),
+        text,
+        "Every category should decode with the mapping"
+    );
+    assert_eq!(
+        decode_obfuscated_text_with_options(
+            "PERSON_2 wrote to EMAIL_1 about FIELD_1 and PERSON_3".to_string(),
+            &mask_words,
+            &options
+        ),
+        "bob wrote to alice@example.com about acme and PERSON_3",
+        "Category placeholders should decode with the same options"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_pseudonyms_keep_kind_and_shape() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Jane Doe"));
+    mask_words.add(&JsValue::from_str("John Smith"));
+    mask_words.add(&JsValue::from_str("jane.doe@corp.io"));
+    mask_words.add(&JsValue::from_str("Initech Inc."));
+    mask_words.add(&category_entry("Springfield", "city"));
+    let options = MaskOptions::new();
+    let mut mapping = PseudonymMapping::new();
+
+    let text = "Jane Doe (jane.doe@corp.io) of Initech Inc. met John Smith in Springfield. JANE DOE agreed.";
+    let masked = mask_text_with_pseudonyms(text.to_string(), &mask_words, &options, &mut mapping);
+    assert_eq!(
+        masked,
+        "Clara Webb (carla.shaw@example.com) of Bluefield Inc. met Lucas Wheeler in Westfield. CLARA WEBB agreed.",
+        "Values should be replaced with fake values of the same kind and shape"
+    );
+    assert_eq!(
+        restore_pseudonyms(masked.clone(), &mapping),
+        text,
+        "Pseudonyms should be reversible with the mapping"
+    );
+    assert_eq!(
+        mapping.original("Lucas Wheeler"),
+        Some("John Smith".to_string())
+    );
+
+    assert_eq!(
+        mask_text_with_pseudonyms(
+            "Call John Smith".to_string(),
+            &mask_words,
+            &options,
+            &mut mapping
+        ),
+        "Call Lucas Wheeler",
+        "A value should keep its pseudonym within a session"
+    );
+    let mut fresh_mapping = PseudonymMapping::new();
+    assert_eq!(
+        mask_text_with_pseudonyms(text.to_string(), &mask_words, &options, &mut fresh_mapping),
+        masked,
+        "The same seed should choose the same pseudonyms"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_pseudonyms_are_distinct_per_value() {
+    let mask_words = Set::new(&JsValue::NULL);
+    for name in [
+        "Anna", "Clara", "Emma", "Grace", "Helen", "Julia", "Laura", "Maria",
+    ] {
+        mask_words.add(&JsValue::from_str(name));
+    }
+    let options = MaskOptions::new();
+    let mut mapping = PseudonymMapping::new();
+    mapping.set_seed(7);
+
+    let text = "Anna, Clara, Emma, Grace, Helen, Julia, Laura and Maria";
+    let masked = mask_text_with_pseudonyms(text.to_string(), &mask_words, &options, &mut mapping);
+    let pseudonyms = mapping.pseudonyms();
+    assert_eq!(pseudonyms.len(), 8);
+    for (index, pseudonym) in pseudonyms.iter().enumerate() {
+        assert!(
+            !pseudonyms[index + 1..].contains(pseudonym),
+            "Each value should get its own pseudonym"
+        );
+        assert!(
+            !mask_words.has(&JsValue::from_str(pseudonym)),
+            "A pseudonym should never be one of the mask words"
+        );
+    }
+    assert_eq!(restore_pseudonyms(masked, &mapping), text);
+}
+
+#[wasm_bindgen_test]
+fn test_pseudonyms_avoid_words_in_text() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("John"));
+    let options = MaskOptions::new();
+    let mut mapping = PseudonymMapping::new();
+    mapping.set_seed(100);
+
+    let text = "John thanked Mark. Mark left.";
+    let masked = mask_text_with_pseudonyms(text.to_string(), &mask_words, &options, &mut mapping);
+    assert_ne!(
+        mapping.pseudonyms(),
+        vec!["Mark".to_string()],
+        "A pseudonym should never be a word already in the text"
+    );
+    assert!(masked.ends_with(" thanked Mark. Mark left."));
+    assert_eq!(restore_pseudonyms(masked, &mapping), text);
+}
+
+#[wasm_bindgen_test]
+fn test_format_preserving_replacement() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("4111-1111-1111-1111"));
+    mask_words.add(&JsValue::from_str("AB12 CDE"));
+    let options = MaskOptions::new();
+    let mut mapping = PseudonymMapping::new();
+    mapping.set_key("team secret".to_string());
+
+    let text = "Card 4111-1111-1111-1111, postcode AB12 CDE, again ab12 cde.";
+    let masked = mask_text_preserving_format(text.to_string(), &mask_words, &options, &mut mapping);
+    assert_eq!(
+        masked, "Card 4342-9319-1778-3701, postcode PD60 JPE, again pd60 jpe.",
+        "Digits, letter cases and separators should be kept"
+    );
+    assert_eq!(
+        restore_pseudonyms(masked.clone(), &mapping),
+        text,
+        "Replacements should be reversible with the mapping"
+    );
+
+    let mut same_key = PseudonymMapping::new();
+    same_key.set_key("team secret".to_string());
+    assert_eq!(
+        mask_text_preserving_format(text.to_string(), &mask_words, &options, &mut same_key),
+        masked,
+        "The same key should produce the same replacements"
+    );
+    let mut other_key = PseudonymMapping::new();
+    other_key.set_key("another secret".to_string());
+    assert_ne!(
+        mask_text_preserving_format(text.to_string(), &mask_words, &options, &mut other_key),
+        masked,
+        "Another key should produce other replacements"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_format_preserving_keeps_case_per_letter_and_script() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("ab12"));
+    mask_words.add(&JsValue::from_str("東京"));
+    let options = MaskOptions::new();
+    let mut mapping = PseudonymMapping::new();
+    mapping.set_key("team secret".to_string());
+
+    let text = "Code aB12 from 東京.";
+    let masked = mask_text_preserving_format(text.to_string(), &mask_words, &options, &mut mapping);
+    let code: Vec<char> = masked["Code ".len()..]
+        .split(' ')
+        .next()
+        .unwrap()
+        .chars()
+        .collect();
+    assert!(
+        code.len() == 4
+            && code[0].is_ascii_lowercase()
+            && code[1].is_ascii_uppercase()
+            && code[2..].iter().all(char::is_ascii_digit),
+        "Every letter should keep its own case in {:?}",
+        masked
+    );
+
+    let city = masked.rsplit(' ').next().unwrap().trim_end_matches('.');
+    assert_eq!(
+        city.chars().count(),
+        2,
+        "The shape should be kept in {:?}",
+        masked
+    );
+    assert!(
+        city.chars().all(|c| ('\u{4E00}'..='\u{9FFF}').contains(&c))
+            && !city.contains('東')
+            && !city.contains('京'),
+        "Letters without case should be replaced within their script in {:?}",
+        masked
+    );
+    assert_eq!(restore_pseudonyms(masked, &mapping), text);
+}
+
+#[wasm_bindgen_test]
+fn test_format_preserving_keeps_the_script_of_digits() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("٠٥٠١٢٣٤٥٦٧"));
+    mask_words.add(&JsValue::from_str("１２３４"));
+    let options = MaskOptions::new();
+    let mut mapping = PseudonymMapping::new();
+    mapping.set_key("team secret".to_string());
+
+    let text = "Call ٠٥٠١٢٣٤٥٦٧ about room １２３４.";
+    let masked = mask_text_preserving_format(text.to_string(), &mask_words, &options, &mut mapping);
+    let phone: Vec<char> = masked["Call ".len()..]
+        .split(' ')
+        .next()
+        .unwrap()
+        .chars()
+        .collect();
+    assert!(
+        phone.len() == 10
+            && phone.iter().all(|c| ('\u{0660}'..='\u{0669}').contains(c))
+            && phone.iter().collect::<String>() != "٠٥٠١٢٣٤٥٦٧",
+        "Arabic-Indic digits should be replaced with Arabic-Indic digits in {:?}",
+        masked
+    );
+
+    let room: Vec<char> = masked
+        .rsplit(' ')
+        .next()
+        .unwrap()
+        .trim_end_matches('.')
+        .chars()
+        .collect();
+    assert!(
+        room.len() == 4
+            && room.iter().all(|c| ('\u{FF10}'..='\u{FF19}').contains(c))
+            && room[0] != '\u{FF10}'
+            && room.iter().collect::<String>() != "１２３４",
+        "Fullwidth digits should be replaced with fullwidth digits in {:?}",
+        masked
+    );
+    assert_eq!(restore_pseudonyms(masked, &mapping), text);
+}
+
+#[wasm_bindgen_test]
+fn test_keyed_tokens_stable_across_sessions() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Alice"));
+    mask_words.add(&alias_entry("Robert", &["Bob"]));
+    let mut options = MaskOptions::new();
+    options.add_token_rule("password".to_string(), 1);
+    let key = TokenKey::new("team secret".to_string());
+
+    let text = "Alice told BOB the password Hunter2";
+    let mut vault = TokenVault::new();
+    let masked = mask_text_with_tokens(text.to_string(), &mask_words, &options, &key, &mut vault);
+    assert_eq!(
+        masked, "TOK_47579f_F told TOK_f6d33d_V1_A the password TOK_96d344",
+        "Aliases should carry their word's token and an alias modifier"
+    );
+    assert_eq!(
+        decode_tokens(masked.clone(), &vault, &options),
+        "Alice told BOB the password Hunter2",
+        "Tokens should decode through the vault, aliases as written"
+    );
+
+    let mut other_vault = TokenVault::new();
+    assert_eq!(
+        mask_text_with_tokens(
+            "alice again".to_string(),
+            &mask_words,
+            &options,
+            &key,
+            &mut other_vault
+        ),
+        "TOK_47579f again",
+        "The same key should give the same token in another session"
+    );
+
+    let rotated_key = TokenKey::new("rotated".to_string());
+    let rotated = rotate_token_key(masked.clone(), &mut vault, &rotated_key);
+    assert_eq!(
+        rotated,
+        "TOK_3a88e3_F told TOK_5d69e0_V1_A the password TOK_8dbaa0"
+    );
+    assert_eq!(
+        decode_tokens(
+            format!("{} / {} / TOK_000000", rotated, masked),
+            &vault,
+            &options
+        ),
+        "Alice told BOB the password Hunter2 / Alice told BOB the password Hunter2 / TOK_000000",
+        "Tokens of both keys should decode and unknown tokens stay literal"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_keyed_tokens_for_name_parts() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("John Smith"));
+    let mut options = MaskOptions::new();
+    options.set_mask_name_parts(true);
+    let key = TokenKey::new("team secret".to_string());
+
+    let text = "John Smith called. Smith left, John stayed.";
+    let mut vault = TokenVault::new();
+    let masked = mask_text_with_tokens(text.to_string(), &mask_words, &options, &key, &mut vault);
+    let token = &masked[.."TOK_3f9a2c".len()];
+    assert_eq!(
+        masked,
+        format!(
+            "{t}_T called. {t}_LAST_F left, {t}_FIRST_F stayed.",
+            t = token
+        ),
+        "Name parts should carry their word's token and a part modifier"
+    );
+    assert_eq!(
+        decode_tokens(masked, &vault, &options),
+        text,
+        "Name parts should decode to the part as written"
+    );
+    assert!(
+        vault.collisions().is_empty(),
+        "Distinct words should not share a token"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_partial_masking_keeps_configured_characters() {
+    let card = Object::new();
+    Reflect::set(
+        &card,
+        &JsValue::from_str("word"),
+        &JsValue::from_str("4111 1111 1111 1111"),
+    )
+    .unwrap();
+    Reflect::set(
+        &card,
+        &JsValue::from_str("keepLast"),
+        &JsValue::from_f64(4.0),
+    )
+    .unwrap();
+    let email = Object::new();
+    Reflect::set(
+        &email,
+        &JsValue::from_str("word"),
+        &JsValue::from_str("jane@example.com"),
+    )
+    .unwrap();
+    Reflect::set(
+        &email,
+        &JsValue::from_str("keepFirst"),
+        &JsValue::from_f64(1.0),
+    )
+    .unwrap();
+    Reflect::set(&email, &JsValue::from_str("keepDomain"), &JsValue::TRUE).unwrap();
+
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&card);
+    mask_words.add(&email);
+    mask_words.add(&category_entry("+44 20 7946 0958", "phone"));
+    mask_words.add(&JsValue::from_str("secret"));
+    let mut options = MaskOptions::new();
+    options.set_category_reveal("phone".to_string(), 0, 0, false, true);
+
+    let text =
+        "Card 4111 1111 1111 1111, mail jane@example.com, call +44 20 7946 0958, code secret.";
+    let expected =
+        "Card ***************1111, mail j***@example.com, call +44*************, code ******.";
+    assert_eq!(
+        mask_text(text.to_string(), &mask_words),
+        "Card ***************1111, mail j***@example.com, call ****************, code ******.",
+        "Entry reveals should apply without options"
+    );
+    assert_eq!(
+        mask_text_with_options(text.to_string(), &mask_words, &options),
+        expected
+    );
+
+    let result = mask_text_with_details(text.to_string(), &mask_words, &options);
+    assert_eq!(result.text(), expected);
+    let revealed: Vec<(String, Vec<String>)> = result
+        .revealed()
+        .iter()
+        .map(|occurrence| (occurrence.masked(), occurrence.visible()))
+        .collect();
+    assert_eq!(
+        revealed,
+        vec![
+            ("***************1111".to_string(), vec!["1111".to_string()]),
+            (
+                "j***@example.com".to_string(),
+                vec!["j".to_string(), "@example.com".to_string()]
+            ),
+            ("+44*************".to_string(), vec!["+44".to_string()]),
+        ],
+        "The result should report what each partial mask left visible"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_mask_length_and_glyph() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("José"));
+    mask_words.add(&JsValue::from_str("Ann"));
+    let text = "José and Ann";
+    let mut options = MaskOptions::new();
+    assert_eq!(
+        mask_text_with_options(text.to_string(), &mask_words, &options),
+        "***** and ***",
+        "The default mask should count bytes"
+    );
+
+    options.set_mask_length(Some("graphemes".to_string()));
+    options.set_mask_glyph(Some("█".to_string()));
+    assert_eq!(
+        mask_text_with_options(text.to_string(), &mask_words, &options),
+        "████ and ███",
+        "Grapheme masks should count characters"
+    );
+
+    options.set_mask_length(Some("fixed".to_string()));
+    options.set_fixed_mask_length(5);
+    options.set_mask_glyph(Some("•".to_string()));
+    assert_eq!(
+        mask_text_with_options(text.to_string(), &mask_words, &options),
+        "••••• and •••••",
+        "Fixed masks should hide the length"
+    );
+
+    options.set_mask_glyph(Some("[REDACTED]".to_string()));
+    assert_eq!(
+        mask_text_with_options(text.to_string(), &mask_words, &options),
+        "[REDACTED] and [REDACTED]",
+        "A longer glyph should replace each value once"
+    );
+
+    options.set_mask_glyph(None);
+    options.set_mask_length(Some("graphemes".to_string()));
+    let email = Object::new();
+    Reflect::set(
+        &email,
+        &JsValue::from_str("word"),
+        &JsValue::from_str("josé@example.com"),
+    )
+    .unwrap();
+    Reflect::set(&email, &JsValue::from_str("keepDomain"), &JsValue::TRUE).unwrap();
+    let emails = Set::new(&JsValue::NULL);
+    emails.add(&email);
+    assert_eq!(
+        mask_text_with_options("josé@example.com".to_string(), &emails, &options),
+        "****@example.com",
+        "Partial masks should use the same mask style"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_unlinkable_placeholders() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Alice"));
+    mask_words.add(&JsValue::from_str("Bob"));
+    let mut options = MaskOptions::new();
+    options.set_unlinkable_placeholders(true);
+
+    let text = "Alice met Bob, then ALICE left.";
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "FIELD_1_F met FIELD_2_F, then FIELD_3_A left.",
+        "Every occurrence should get its own field number"
+    );
+    assert_eq!(
+        decode_with_mapping(result.text(), &result.mapping()),
+        text,
+        "Unlinkable placeholders should decode with the mapping"
+    );
+    assert_eq!(
+        result.mapping().linked_fields(3),
+        vec![1, 3],
+        "The mapping should link the occurrences of the same word"
+    );
+    assert_eq!(result.mapping().linked_fields(2), vec![2]);
+}
+
+#[wasm_bindgen_test]
+fn test_placeholder_like_text_is_escaped() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Alice"));
+    mask_words.add(&JsValue::from_str("Bob"));
+
+    let text = r"Alice wrote FIELD_2 and FIELD_\1 in the template.";
+    let masked = mask_text_with_fields(text.to_string(), &mask_words);
+    assert_eq!(
+        masked, r"FIELD_1_F wrote FIELD_\2 and FIELD_\\1 in the template.",
+        "Placeholder-like text should be escaped"
+    );
+    assert_eq!(
+        decode_obfuscated_text(masked, &mask_words),
+        text,
+        "Escaped text should decode untouched"
+    );
+
+    let mut options = MaskOptions::new();
+    options.add_token_rule("token".to_string(), 1);
+    let text = "alice token=FIELD_3 end";
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(
+        result.text(),
+        "FIELD_1 token=FIELD_3_A end",
+        "Matched values should not include escapes"
+    );
+    assert_eq!(decode_with_mapping(result.text(), &result.mapping()), text);
+
+    let person_words = Set::new(&JsValue::NULL);
+    person_words.add(&category_entry("Bob", "person"));
+    let mut options = MaskOptions::new();
+    options.set_placeholder_template(Some("{category}_{n}".to_string()));
+    let text = "Bob said PERSON_1, not ISO_9001 or file_2.";
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &person_words, &options);
+    assert_eq!(
+        result.text(),
+        r"PERSON_1_F said PERSON_\1, not ISO_9001 or file_2.",
+        "Only text after a label in use should be escaped"
+    );
+    assert_eq!(decode_with_mapping(result.text(), &result.mapping()), text);
+}
+
+#[wasm_bindgen_test]
+fn test_session_namespaces_decode_mixed_text() {
+    let first_words = Set::new(&JsValue::NULL);
+    first_words.add(&JsValue::from_str("Alice"));
+    let mut first_options = MaskOptions::new();
+    first_options.set_session_namespace(Some("a7".to_string()));
+    let first = mask_text_with_fields_and_mapping(
+        "Alice called.".to_string(),
+        &first_words,
+        &first_options,
+    );
+    assert_eq!(first.text(), "FIELD_a7_1_F called.");
+
+    let second_words = Set::new(&JsValue::NULL);
+    second_words.add(&JsValue::from_str("Bob"));
+    let mut second_options = MaskOptions::new();
+    second_options.set_namespace_sessions(true);
+    let second = mask_text_with_fields_and_mapping(
+        "Bob answered.".to_string(),
+        &second_words,
+        &second_options,
+    );
+    let namespace = second
+        .mapping()
+        .session_namespace()
+        .expect("A namespace should be generated");
+    assert_eq!(second.text(), format!("FIELD_{}_1_F answered.", namespace));
+
+    let mixed = format!("{} {} FIELD_1 stays.", first.text(), second.text());
+    assert_eq!(
+        decode_with_mappings(mixed, vec![first.mapping(), second.mapping()]),
+        "Alice called. Bob answered. FIELD_1 stays.",
+        "Each session should decode with its own mapping"
+    );
+    assert_eq!(
+        decode_obfuscated_text_with_options(first.text(), &first_words, &first_options),
+        "Alice called.",
+        "A namespace set in the options should decode without a mapping"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_check_characters_reject_invented_fields() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Alice"));
+    mask_words.add(&JsValue::from_str("Bob"));
+    let mut options = MaskOptions::new();
+    options.set_check_characters(true);
+
+    let text = "Alice met Bob.";
+    let result = mask_text_with_fields_and_mapping(text.to_string(), &mask_words, &options);
+    assert_eq!(result.text(), "FIELD_1B_F met FIELD_2C_F.");
+    assert_eq!(decode_with_mapping(result.text(), &result.mapping()), text);
+
+    let reply = "FIELD_2C_F thanked FIELD_1B_F, FIELD_7 and FIELD_1C.";
+    let decoded = decode_with_details(reply.to_string(), &result.mapping());
+    assert_eq!(
+        decoded.text(),
+        "Bob thanked Alice, FIELD_7 and FIELD_1C.",
+        "Placeholders failing validation should stay undecoded"
+    );
+    assert_eq!(
+        decoded.rejected(),
+        vec!["FIELD_7".to_string(), "FIELD_1C".to_string()],
+        "Placeholders failing validation should be reported"
+    );
+    assert_eq!(
+        decode_obfuscated_text_with_options(reply.to_string(), &mask_words, &options),
+        "Bob thanked Alice, FIELD_7 and FIELD_1C.",
+        "Check characters should also be validated without a mapping"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_lenient_decoding_reports_mangled_placeholders() {
+    let mask_words = Set::new(&JsValue::NULL);
+    mask_words.add(&JsValue::from_str("Alice"));
+    mask_words.add(&JsValue::from_str("Bob"));
+    mask_words.add(&JsValue::from_str("Acme"));
+    let result = mask_text_with_fields_and_mapping(
+        "Alice, Bob and Acme".to_string(),
+        &mask_words,
+        &MaskOptions::new(),
+    );
+    assert_eq!(result.text(), "FIELD_1_F, FIELD_3_F and FIELD_2_F");
+
+    let reply =
+        "Field_1 met FIELD 3, field_2_a and FIELD-3_F. **FIELD_1_F** read FIELD_2_F's note.";
+    let decoded = decode_leniently(reply.to_string(), &result.mapping());
+    assert_eq!(
+        decoded.text(),
+        "alice met bob, ACME and Bob. **Alice** read Acme's note.",
+        "Mangled placeholders should decode with their case modifiers"
+    );
+    let lenient_matches = decoded.lenient_matches();
+    assert_eq!(
+        lenient_matches
+            .iter()
+            .map(|found| found.found())
+            .collect::<Vec<_>>(),
+        vec!["Field_1", "FIELD 3", "field_2_a", "FIELD-3_F"],
+        "Only the non-canonical placeholders should be reported"
+    );
+    assert_eq!(
+        lenient_matches
+            .iter()
+            .map(|found| found.canonical())
+            .collect::<Vec<_>>(),
+        vec!["FIELD_1", "FIELD_3", "FIELD_2_A", "FIELD_3_F"]
+    );
+    assert_eq!(lenient_matches[2].decoded(), "ACME");
+    assert_eq!(
+        decode_with_mapping(reply.to_string(), &result.mapping()),
+        "Field_1 met FIELD 3, field_2_a and FIELD-3_F. **Alice** read Acme's note.",
+        "Strict decoding should only accept canonical placeholders"
+    );
+
+    let prose = "FIELD_1 fills in field 1 of the form, then Field 2.";
+    assert_eq!(
+        decode_leniently(prose.to_string(), &result.mapping()).text(),
+        "alice fills in field 1 of the form, then Field 2.",
+        "Changes of both case and separator should not be accepted"
+    );
+}